@@ -0,0 +1,162 @@
+use crate::error::{HdmiSwitchError, Result};
+use crate::port::{AudioOutput, Input, Output};
+use crate::table::{Column, Table};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Where audio can be sent: the embedded audio of an HDMI output or one of
+/// the analog audio outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioTarget {
+    Hdmi(Output),
+    Analog(AudioOutput),
+}
+
+impl fmt::Display for AudioTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioTarget::Hdmi(output) => write!(f, "{}", output),
+            AudioTarget::Analog(output) => write!(f, "{}", output),
+        }
+    }
+}
+
+/// Parses the API names of the physical outputs, `all` is refused as audio
+/// is queried and set one output at a time.
+impl FromStr for AudioTarget {
+    type Err = HdmiSwitchError;
+
+    fn from_str(target: &str) -> Result<Self> {
+        match (target.parse::<Output>(), target.parse::<AudioOutput>()) {
+            (Ok(Output::All), _) => Err(HdmiSwitchError::InvalidPort(
+                "audio is set one output at a time, all can't be used".to_string(),
+            )),
+            (Ok(output), _) => Ok(AudioTarget::Hdmi(output)),
+            (_, Ok(output)) => Ok(AudioTarget::Analog(output)),
+            _ => Err(HdmiSwitchError::InvalidPort(format!(
+                "{} is not a supported HDMI or audio output",
+                target
+            ))),
+        }
+    }
+}
+
+impl Serialize for AudioTarget {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The audio an output plays. The API names are `follow`, an input such as
+/// `hdmiin2` and e.g. `arc_hdmiout4` for the audio a display returns over ARC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioSource {
+    /// The audio of whichever input is routed to the HDMI output, for analog
+    /// outputs the HDMI output with the same number.
+    Follow,
+    Input(Input),
+    Arc(Output),
+}
+
+pub(crate) const FOLLOW: &str = "follow";
+pub(crate) const ARC_PREFIX: &str = "arc_";
+
+impl fmt::Display for AudioSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioSource::Follow => f.write_str(FOLLOW),
+            AudioSource::Input(input) => write!(f, "{}", input),
+            AudioSource::Arc(output) => write!(f, "{}{}", ARC_PREFIX, output),
+        }
+    }
+}
+
+/// Parses the API names, see [`crate::utils::Switch::resolve_audio_source`]
+/// for the names accepted on the command line.
+impl FromStr for AudioSource {
+    type Err = HdmiSwitchError;
+
+    fn from_str(source: &str) -> Result<Self> {
+        if source.eq_ignore_ascii_case(FOLLOW) {
+            return Ok(AudioSource::Follow);
+        }
+
+        let arc = source
+            .get(..ARC_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(ARC_PREFIX))
+            .map(|_prefix| &source[ARC_PREFIX.len()..]);
+        match arc {
+            Some(output) => match output.parse::<Output>()? {
+                Output::All => Err(HdmiSwitchError::InvalidPort(format!(
+                    "{} is not a supported audio source",
+                    source
+                ))),
+                output => Ok(AudioSource::Arc(output)),
+            },
+            None => Ok(AudioSource::Input(source.parse()?)),
+        }
+    }
+}
+
+impl Serialize for AudioSource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The audio settings of an output as queried from the switch. `arc` is only
+/// known for HDMI outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AudioStatus {
+    pub output: AudioTarget,
+    pub source: AudioSource,
+    pub muted: bool,
+    pub arc: Option<bool>,
+}
+
+/// Serializable audio settings of each output, as printed by `audio get`.
+#[derive(Serialize, Debug)]
+pub struct AudioView {
+    pub audio: Vec<OutputAudioView>,
+}
+
+/// An [`AudioStatus`] along with how the output and source are displayed,
+/// e.g. `hdmiout4 TV (tv)`.
+#[derive(Serialize, Debug)]
+pub struct OutputAudioView {
+    pub output: AudioTarget,
+    pub output_display: String,
+    pub source: AudioSource,
+    pub source_display: String,
+    pub muted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arc: Option<bool>,
+}
+
+impl AudioView {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new(), Column::new()]);
+
+        table.heading(0, "Audio:");
+        for output in self.audio.iter() {
+            let mut notes: Vec<&str> = Vec::new();
+            if output.muted {
+                notes.push("muted");
+            }
+            if output.arc == Some(true) {
+                notes.push("ARC on");
+            }
+            table.row(
+                2,
+                vec![
+                    format!("{}:", output.output_display),
+                    output.source_display.clone(),
+                    notes.join(", "),
+                ],
+            );
+        }
+
+        table
+    }
+}
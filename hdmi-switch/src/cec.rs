@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// HDMI-CEC commands the switch can send to the display on an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CecCommand {
+    PowerOn,
+    PowerOff,
+    /// Makes the display show the HDMI input the switch is connected to.
+    InputSource,
+    VolumeUp,
+    VolumeDown,
+    /// Toggles the display's mute.
+    Mute,
+}
+
+impl CecCommand {
+    /// Name the switch API uses for the command.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CecCommand::PowerOn => "on",
+            CecCommand::PowerOff => "off",
+            CecCommand::InputSource => "source",
+            CecCommand::VolumeUp => "volup",
+            CecCommand::VolumeDown => "voldown",
+            CecCommand::Mute => "mute",
+        }
+    }
+}
+
+impl fmt::Display for CecCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,341 @@
+use crate::audio::{AudioSource, AudioStatus, AudioTarget};
+use crate::cec::CecCommand;
+use crate::configuration::{Configuration, RouteConfiguration};
+use crate::edid::Edid;
+use crate::port::{Input, Output};
+use crate::transport::{self, Transport};
+use crate::utils::Switch;
+use crate::error::{HdmiSwitchError, Result};
+use serde::Serialize;
+use std::time::Duration;
+
+/// An output and the input that is routed to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Route {
+    pub input: Input,
+    pub output: Output,
+}
+
+/// What [`SwitchClient::apply_routes`] did: every route, and the displays
+/// that didn't power on along with why.
+#[derive(Debug)]
+pub struct AppliedRoutes {
+    pub routes: Vec<Route>,
+    pub power_on_failures: Vec<(Output, HdmiSwitchError)>,
+}
+
+/// A session with the HDMI switch over any [`Transport`]. Inputs and outputs
+/// passed to the methods may be either API names such as `hdmiin1` or
+/// configured aliases.
+pub struct SwitchClient {
+    transport: Box<dyn Transport>,
+    switch: Switch,
+    buffer: Vec<u8>,
+    read_timeout: Duration,
+}
+
+impl SwitchClient {
+    /// Connects to the switch described by `configuration` and consumes the
+    /// welcome message it sends on connect, if any. With `devices` configured
+    /// this is the default device, see [`Configuration::device`] for others.
+    pub fn connect(configuration: &Configuration) -> Result<Self> {
+        if !configuration.devices.is_empty() {
+            return SwitchClient::connect(&configuration.device(None)?);
+        }
+
+        let switch = Switch::from_configuration(configuration)?;
+        let transport = transport::open(configuration)?;
+
+        SwitchClient::with_transport(transport, switch, configuration.get_read_timeout())
+    }
+
+    /// Starts a session over an already open `transport`.
+    pub fn with_transport(
+        transport: Box<dyn Transport>,
+        switch: Switch,
+        read_timeout: Duration,
+    ) -> Result<Self> {
+        let mut client = SwitchClient {
+            transport,
+            switch,
+            buffer: Vec::new(),
+            read_timeout,
+        };
+
+        if client.transport.has_welcome_message() {
+            client.skip_welcome_message()?;
+        }
+
+        Ok(client)
+    }
+
+    /// The alias tables used to resolve ports for this session.
+    pub fn switch(&self) -> &Switch {
+        &self.switch
+    }
+
+    /// Routes `input` to `output` and waits for the switch to acknowledge it.
+    pub fn route(&mut self, input: &str, output: &str) -> Result<Route> {
+        let route = self.switch.resolve_route(input, output)?;
+        self.send_route(&route)?;
+
+        Ok(route)
+    }
+
+    fn send_route(&mut self, route: &Route) -> Result<()> {
+        let buffer: String = self.switch.profile().route_command(route.input, route.output);
+        let response = self.send(&buffer)?;
+        self.switch
+            .profile()
+            .verify_route_response(route.input, route.output, &response)
+    }
+
+    /// Applies `routes` in order, then powers on the display of those with
+    /// `power_on`, of every output for `all`. Every route is resolved before
+    /// the first one is sent, so an unknown alias leaves the switch untouched.
+    /// A display failing to power on doesn't stop the others, failures are
+    /// returned with the routes.
+    pub fn apply_routes(&mut self, routes: &[RouteConfiguration]) -> Result<AppliedRoutes> {
+        let mut resolved: Vec<Route> = Vec::new();
+        let mut power_on: Vec<Output> = Vec::new();
+        for route in routes.iter() {
+            let resolved_route = self.switch.resolve_route(&route.input, &route.output)?;
+            if route.power_on {
+                match resolved_route.output {
+                    Output::All => power_on.extend(self.switch.profile().outputs()),
+                    output => power_on.push(output),
+                }
+            }
+            resolved.push(resolved_route);
+        }
+
+        for route in resolved.iter() {
+            self.send_route(route)?;
+        }
+        let mut power_on_failures: Vec<(Output, HdmiSwitchError)> = Vec::new();
+        for output in power_on {
+            if let Err(err) = self.send_cec(output, CecCommand::PowerOn) {
+                power_on_failures.push((output, err));
+            }
+        }
+
+        Ok(AppliedRoutes {
+            routes: resolved,
+            power_on_failures,
+        })
+    }
+
+    /// Sends `command` to the display on `output` over HDMI-CEC.
+    pub fn cec(&mut self, output: &str, command: CecCommand) -> Result<Output> {
+        let output = self.switch.resolve_cec_output(output, command)?;
+        self.send_cec(output, command)?;
+
+        Ok(output)
+    }
+
+    fn send_cec(&mut self, output: Output, command: CecCommand) -> Result<()> {
+        let buffer: String = self.switch.profile().cec_command(output, command);
+        let response = self.send(&buffer)?;
+        self.switch
+            .profile()
+            .verify_cec_response(output, command, &response)
+    }
+
+    /// Applies every route of the scene called `name` in order.
+    pub fn apply_scene(&mut self, name: &str) -> Result<AppliedRoutes> {
+        let routes = self.switch.scene(name)?.to_vec();
+        self.apply_routes(&routes)
+    }
+
+    /// Routes `input` to every output at once.
+    pub fn routes_all(&mut self, input: &str) -> Result<Route> {
+        self.route(input, Output::All.as_str())
+    }
+
+    /// Queries the input currently routed to each output.
+    pub fn routing(&mut self) -> Result<Vec<Route>> {
+        let mut routing: Vec<Route> = Vec::new();
+        for output in self.switch.profile().outputs() {
+            let buffer: String = self.switch.profile().routing_query(*output);
+            let response = self.send(&buffer)?;
+            let (input, output) = self.switch.profile().parse_routing_response(&response)?;
+            routing.push(Route { input, output });
+        }
+
+        Ok(routing)
+    }
+
+    /// Queries the EDID `input` advertises.
+    pub fn edid(&mut self, input: &str) -> Result<(Input, Edid)> {
+        let input = self.switch.resolve_input(input)?;
+        self.query_edid(input)
+    }
+
+    /// Queries the EDID of every input.
+    pub fn edids(&mut self) -> Result<Vec<(Input, Edid)>> {
+        let mut edids: Vec<(Input, Edid)> = Vec::new();
+        for input in self.switch.profile().inputs() {
+            edids.push(self.query_edid(*input)?);
+        }
+
+        Ok(edids)
+    }
+
+    fn query_edid(&mut self, input: Input) -> Result<(Input, Edid)> {
+        let buffer: String = self.switch.profile().edid_query(input);
+        let response = self.send(&buffer)?;
+
+        self.switch.profile().parse_edid_response(&response)
+    }
+
+    /// Makes `input` advertise `edid`, a preset or an output to copy the EDID
+    /// from, see [`Switch::resolve_edid`].
+    pub fn set_edid(&mut self, input: &str, edid: &str) -> Result<(Input, Edid)> {
+        let (input, edid) = (self.switch.resolve_input(input)?, self.switch.resolve_edid(edid)?);
+
+        let buffer: String = self.switch.profile().edid_command(input, edid);
+        let response = self.send(&buffer)?;
+        self.switch
+            .profile()
+            .verify_edid_response(input, edid, &response)?;
+
+        Ok((input, edid))
+    }
+
+    /// Queries the audio source, mute and ARC state of `target`, an HDMI or
+    /// analog audio output, see [`Switch::resolve_audio_target`].
+    pub fn audio(&mut self, target: &str) -> Result<AudioStatus> {
+        let target = self.switch.resolve_audio_target(target)?;
+        self.query_audio(target)
+    }
+
+    /// Queries the audio of every HDMI output, then every analog output.
+    pub fn audios(&mut self) -> Result<Vec<AudioStatus>> {
+        let profile = self.switch.profile();
+        let targets: Vec<AudioTarget> = profile
+            .outputs()
+            .iter()
+            .map(|output| AudioTarget::Hdmi(*output))
+            .chain(profile.audio_outputs().iter().map(|output| AudioTarget::Analog(*output)))
+            .collect();
+
+        let mut audio: Vec<AudioStatus> = Vec::new();
+        for target in targets {
+            audio.push(self.query_audio(target)?);
+        }
+
+        Ok(audio)
+    }
+
+    fn query_audio(&mut self, target: AudioTarget) -> Result<AudioStatus> {
+        let buffer: String = self.switch.profile().audio_query(target);
+        let response = self.send(&buffer)?;
+        let (output, source) = self.switch.profile().parse_audio_response(&response)?;
+
+        let buffer: String = self.switch.profile().mute_query(target);
+        let response = self.send(&buffer)?;
+        let (_output, muted) = self.switch.profile().parse_mute_response(&response)?;
+
+        let arc = match target {
+            AudioTarget::Hdmi(hdmi) => {
+                let buffer: String = self.switch.profile().arc_query(hdmi);
+                let response = self.send(&buffer)?;
+                Some(self.switch.profile().parse_arc_response(&response)?.1)
+            }
+            AudioTarget::Analog(_analog) => None,
+        };
+
+        Ok(AudioStatus {
+            output,
+            source,
+            muted,
+            arc,
+        })
+    }
+
+    /// Makes `target` play `source`, see [`Switch::resolve_audio_source`].
+    pub fn set_audio(&mut self, target: &str, source: &str) -> Result<(AudioTarget, AudioSource)> {
+        let (target, source) = self.switch.resolve_audio_change(target, source)?;
+
+        let buffer: String = self.switch.profile().audio_command(target, source);
+        let response = self.send(&buffer)?;
+        self.switch
+            .profile()
+            .verify_audio_response(target, source, &response)?;
+
+        Ok((target, source))
+    }
+
+    /// Mutes or unmutes the audio of `target`.
+    pub fn set_mute(&mut self, target: &str, muted: bool) -> Result<AudioTarget> {
+        let target = self.switch.resolve_mute_target(target)?;
+
+        let buffer: String = self.switch.profile().mute_command(target, muted);
+        let response = self.send(&buffer)?;
+        self.switch
+            .profile()
+            .verify_mute_response(target, muted, &response)?;
+
+        Ok(target)
+    }
+
+    /// Turns the audio return channel of `output` on or off.
+    pub fn set_arc(&mut self, output: &str, on: bool) -> Result<Output> {
+        let output = self.switch.resolve_arc_output(output)?;
+
+        let buffer: String = self.switch.profile().arc_command(output, on);
+        let response = self.send(&buffer)?;
+        self.switch.profile().verify_arc_response(output, on, &response)?;
+
+        Ok(output)
+    }
+
+    fn send(&mut self, buffer: &str) -> Result<String> {
+        self.transport.write(buffer.as_bytes())?;
+
+        self.read_response()
+    }
+
+    /// Reads from the HDMI switch until it has sent a full, non-empty line and
+    /// returns that line without its line terminator.
+    fn read_response(&mut self) -> Result<String> {
+        loop {
+            while let Some(end) = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+                let line = String::from_utf8_lossy(&self.buffer[..end]).trim().to_string();
+                self.buffer.drain(..=end);
+                if !line.is_empty() {
+                    return Ok(line);
+                }
+            }
+
+            let data = self.read_data("waiting for a response from the HDMI switch")?;
+            self.buffer.extend_from_slice(&data);
+        }
+    }
+
+    /// Drops the welcome message up to its line terminator. It may arrive in
+    /// several reads, anything after it is kept for [`SwitchClient::read_response`].
+    fn skip_welcome_message(&mut self) -> Result<()> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+                self.buffer.drain(..=end);
+                return Ok(());
+            }
+
+            let data = self.read_data("waiting for the HDMI switch welcome message")?;
+            self.buffer.extend_from_slice(&data);
+        }
+    }
+
+    /// Reads the next data from the transport, giving up after the configured
+    /// read timeout. `action` describes what was being waited for in the error.
+    fn read_data(&mut self, action: &str) -> Result<Vec<u8>> {
+        match self.transport.read(self.read_timeout)? {
+            Some(data) => Ok(data),
+            None => Err(HdmiSwitchError::Timeout {
+                action: action.to_string(),
+                timeout: self.read_timeout,
+            }),
+        }
+    }
+}
@@ -1,45 +1,782 @@
+use crate::device::Model;
+use crate::edid::EdidPreset;
+use crate::error::{HdmiSwitchError, Result};
+use crate::port::{AudioOutput, Input, Output};
+use indexmap::IndexMap;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::error::Error;
+use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::fs;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Version of the configuration schema this build reads and writes.
+pub const CONFIGURATION_VERSION: u32 = 1;
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Configuration {
+    /// Schema version the file was written for, files without one are taken
+    /// to be version 1.
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Device used when `--device` isn't given and there are several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_device: Option<String>,
+    /// Required unless every device under `devices` sets its own host.
+    #[serde(default)]
     pub server: ServerConfiguration,
+    #[serde(default, deserialize_with = "alias_section")]
     pub input: InputConfiguration,
+    #[serde(default, deserialize_with = "alias_section")]
     pub output: OutputConfiguration,
+    #[serde(
+        default,
+        deserialize_with = "alias_section",
+        skip_serializing_if = "AudioConfiguration::is_empty"
+    )]
+    pub audio: AudioConfiguration,
+    #[serde(default, deserialize_with = "unique_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub scenes: IndexMap<String, Vec<RouteConfiguration>>,
+    /// Named switches. Each one starts from the top-level settings and adds
+    /// or overrides its own.
+    #[serde(default, deserialize_with = "unique_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub devices: IndexMap<String, DeviceConfiguration>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// One switch under `devices`, with the same sections as the top level.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfiguration {
+    #[serde(default)]
+    pub server: ServerConfiguration,
+    #[serde(default, deserialize_with = "alias_section")]
+    pub input: InputConfiguration,
+    #[serde(default, deserialize_with = "alias_section")]
+    pub output: OutputConfiguration,
+    #[serde(
+        default,
+        deserialize_with = "alias_section",
+        skip_serializing_if = "AudioConfiguration::is_empty"
+    )]
+    pub audio: AudioConfiguration,
+    #[serde(default, deserialize_with = "unique_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub scenes: IndexMap<String, Vec<RouteConfiguration>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct ServerConfiguration {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
+    /// Seconds to wait for the TCP connection, defaults to 5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<f64>,
+    /// Seconds to wait for each response from the switch, defaults to 5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<Model>,
+    /// How to reach the switch, defaults to telnet on `host` and `port`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<TransportConfiguration>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The connection commands are sent over, selected with `type`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum TransportConfiguration {
+    /// Telnet to `server.host` and `server.port`.
+    Telnet,
+    /// A plain TCP socket on `server.host` and `server.port`, with no telnet
+    /// processing of the bytes sent either way.
+    RawTcp {
+        /// Whether the switch greets each connection, as the AV Access
+        /// firmware does. IP-to-serial gateways usually don't.
+        #[serde(default, skip_serializing_if = "is_false")]
+        welcome_message: bool,
+    },
+    /// The RS-232 control port, e.g. through a USB adapter.
+    Serial {
+        /// Serial device, e.g. `/dev/ttyUSB0`.
+        path: String,
+        /// Defaults to 115200, what the switch ships with.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        baud_rate: Option<u32>,
+        #[serde(default, skip_serializing_if = "Parity::is_none")]
+        parity: Parity,
+    },
+}
+
+impl TransportConfiguration {
+    /// Whether the transport connects to `server.host` and `server.port`.
+    pub fn uses_network(&self) -> bool {
+        match self {
+            TransportConfiguration::Telnet | TransportConfiguration::RawTcp { .. } => true,
+            TransportConfiguration::Serial { .. } => false,
+        }
+    }
+}
+
+/// Parity bit of a serial transport.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+impl Parity {
+    fn is_none(&self) -> bool {
+        *self == Parity::None
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct InputConfiguration {
-    pub aliases: HashMap<String, String>,
+    #[serde(default, deserialize_with = "alias_keys")]
+    pub aliases: IndexMap<String, Input>,
+    #[serde(default, deserialize_with = "unique_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub ports: IndexMap<Input, PortConfiguration>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct OutputConfiguration {
-    pub aliases: HashMap<String, String>,
+    #[serde(default, deserialize_with = "alias_keys")]
+    pub aliases: IndexMap<String, Output>,
+    #[serde(default, deserialize_with = "unique_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub ports: IndexMap<Output, PortConfiguration>,
+}
+
+/// Aliases and labels of the analog audio outputs, like those of the HDMI
+/// outputs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AudioConfiguration {
+    #[serde(default, deserialize_with = "alias_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub aliases: IndexMap<String, AudioOutput>,
+    #[serde(default, deserialize_with = "unique_keys", skip_serializing_if = "IndexMap::is_empty")]
+    pub ports: IndexMap<AudioOutput, PortConfiguration>,
+}
+
+impl AudioConfiguration {
+    fn is_empty(&self) -> bool {
+        self.aliases.is_empty() && self.ports.is_empty()
+    }
+}
+
+/// Labels of a physical port, keyed by its API name under `ports`. Aliases
+/// listed here work the same as the ones in the flat `aliases` map.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PortConfiguration {
+    /// Name shown next to the API name, e.g. `Living room TV`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "alias_list", skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Refuse to route to or from this port, e.g. while nothing is plugged in.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub disabled: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn default_version() -> u32 {
+    CONFIGURATION_VERSION
+}
+
+/// A single route of a scene. Both sides accept aliases as well as API names.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfiguration {
+    pub input: String,
+    pub output: String,
+    /// Power on the display on `output` over HDMI-CEC once routed.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub power_on: bool,
+}
+
+/// Parses the `input:output` form used on the command line, e.g. `pc:tv`.
+impl FromStr for RouteConfiguration {
+    type Err = HdmiSwitchError;
+
+    fn from_str(route: &str) -> Result<Self> {
+        match route.split_once(':') {
+            Some((input, output)) if !input.is_empty() && !output.is_empty() => {
+                Ok(RouteConfiguration {
+                    input: input.to_string(),
+                    output: output.to_string(),
+                    power_on: false,
+                })
+            }
+            _ => Err(HdmiSwitchError::Usage(format!(
+                "Route {} must be in the form input:output",
+                route
+            ))),
+        }
+    }
 }
 
 impl Configuration {
     pub fn get_port(&self) -> u16 {
-        let port = match self.server.port {
-            Some(port) => port,
-            _ => 23,
+        self.server.port.unwrap_or(23)
+    }
+
+    pub fn get_connect_timeout(&self) -> Duration {
+        Duration::from_secs_f64(self.server.connect_timeout.unwrap_or(5.0))
+    }
+
+    pub fn get_read_timeout(&self) -> Duration {
+        Duration::from_secs_f64(self.server.read_timeout.unwrap_or(5.0))
+    }
+
+    pub fn get_model(&self) -> Model {
+        self.server.model.unwrap_or_default()
+    }
+
+    pub fn get_transport(&self) -> TransportConfiguration {
+        self.server.transport.clone().unwrap_or(TransportConfiguration::Telnet)
+    }
+
+    /// Where the switch is reached, e.g. `10.0.0.5:23` or `/dev/ttyUSB0`.
+    pub fn get_address(&self) -> String {
+        match self.get_transport() {
+            TransportConfiguration::Serial { path, .. } => path,
+            TransportConfiguration::Telnet | TransportConfiguration::RawTcp { .. } => {
+                format!("{}:{}", self.server.host, self.get_port())
+            }
+        }
+    }
+
+    /// Names of the switches under `devices`, in file order.
+    pub fn device_names(&self) -> Vec<&str> {
+        self.devices.keys().map(String::as_str).collect()
+    }
+
+    /// Name of the device used without `--device`: `default_device`, or the
+    /// only device there is. `None` if no devices are configured.
+    pub fn default_device_name(&self) -> Result<Option<&str>> {
+        if let Some(name) = &self.default_device {
+            return Ok(Some(name.as_str()));
+        }
+
+        match self.device_names().as_slice() {
+            [] => Ok(None),
+            [name] => Ok(Some(name)),
+            names => Err(HdmiSwitchError::Usage(format!(
+                "Several devices are configured ({}), choose one with --device or set default_device",
+                names.join(", ")
+            ))),
+        }
+    }
+
+    /// The configuration of a single switch: the device called `name`, or the
+    /// default one, on top of the top-level settings. Without devices the
+    /// top-level settings are the switch, and `name` must not be given.
+    pub fn device(&self, name: Option<&str>) -> Result<Configuration> {
+        let name = match name {
+            Some(name) => name,
+            None => match self.default_device_name()? {
+                Some(name) => name,
+                None => return Ok(self.with_device(&DeviceConfiguration::default())),
+            },
+        };
+
+        match self.devices.get(name) {
+            Some(device) => Ok(self.with_device(device)),
+            None => Err(HdmiSwitchError::UnknownDevice(name.to_string())),
+        }
+    }
+
+    /// The top-level settings with those of `device` added, the device
+    /// winning where both set the same one, see [`merge_section`].
+    fn with_device(&self, device: &DeviceConfiguration) -> Configuration {
+        let (server, base) = (&device.server, &self.server);
+        let host = match server.host.is_empty() {
+            true => base.host.clone(),
+            false => server.host.clone(),
         };
 
-        return port;
+        let mut input = self.input.clone();
+        merge_section(
+            &mut input.aliases,
+            &mut input.ports,
+            &device.input.aliases,
+            &device.input.ports,
+        );
+        let mut output = self.output.clone();
+        merge_section(
+            &mut output.aliases,
+            &mut output.ports,
+            &device.output.aliases,
+            &device.output.ports,
+        );
+        let mut audio = self.audio.clone();
+        merge_section(
+            &mut audio.aliases,
+            &mut audio.ports,
+            &device.audio.aliases,
+            &device.audio.ports,
+        );
+        let mut scenes = self.scenes.clone();
+        scenes.extend(device.scenes.clone());
+
+        Configuration {
+            version: self.version,
+            default_device: None,
+            server: ServerConfiguration {
+                host,
+                port: server.port.or(base.port),
+                connect_timeout: server.connect_timeout.or(base.connect_timeout),
+                read_timeout: server.read_timeout.or(base.read_timeout),
+                model: server.model.or(base.model),
+                transport: server.transport.clone().or_else(|| base.transport.clone()),
+            },
+            input,
+            output,
+            audio,
+            scenes,
+            devices: IndexMap::new(),
+        }
+    }
+
+    /// Checks the values that can't be used at all, see
+    /// [`crate::validation::validate`] for a thorough check.
+    pub fn check(&self) -> Result<()> {
+        check_version(self.version)?;
+        if let Some(name) = &self.default_device {
+            if !self.devices.contains_key(name) {
+                return Err(HdmiSwitchError::UnknownDevice(name.to_string()));
+            }
+        }
+        let needs_host = self.devices.is_empty() && self.get_transport().uses_network();
+        if needs_host && self.server.host.is_empty() {
+            return Err(HdmiSwitchError::InvalidConfiguration(
+                "server.host is required".to_string(),
+            ));
+        }
+        for (key, timeout) in [
+            ("server.connect_timeout", self.server.connect_timeout),
+            ("server.read_timeout", self.server.read_timeout),
+        ] {
+            if let Some(timeout) = timeout {
+                check_timeout(timeout).map_err(|err| {
+                    HdmiSwitchError::InvalidConfiguration(format!("{}: {}", key, err))
+                })?;
+            }
+        }
+
+        Ok(())
     }
 }
 
-pub fn get_configuration(file_path: String) -> Result<Configuration, Box<dyn Error>> {
-    let contents = fs::read_to_string(file_path.as_str())?;
-    let configuration: Configuration = serde_yaml::from_str(contents.as_str())?;
+/// Adds the aliases and ports of a device section to the top-level ones. A
+/// port the device configures replaces the top-level one, and an alias the
+/// device defines, in its flat map or under a port, replaces the top-level
+/// alias of that name wherever that one is defined.
+fn merge_section<P: Hash + Eq + Clone>(
+    aliases: &mut IndexMap<String, P>,
+    ports: &mut IndexMap<P, PortConfiguration>,
+    device_aliases: &IndexMap<String, P>,
+    device_ports: &IndexMap<P, PortConfiguration>,
+) {
+    let redefined: Vec<&String> = device_aliases
+        .keys()
+        .chain(device_ports.values().flat_map(|port| port.aliases.iter()))
+        .collect();
+    aliases.retain(|alias, _port| !redefined.contains(&alias));
+    for port in ports.values_mut() {
+        port.aliases.retain(|alias| !redefined.contains(&alias));
+    }
+
+    aliases.extend(device_aliases.clone());
+    ports.extend(device_ports.clone());
+}
+
+/// Refuses aliases that would hide one of the names the switch API uses,
+/// e.g. `hdmiin1`, `audioout1` or `all`, see also [`check_keyword`].
+pub fn check_alias(alias: &str) -> Result<(), String> {
+    if alias.parse::<Input>().is_ok()
+        || alias.parse::<Output>().is_ok()
+        || alias.parse::<AudioOutput>().is_ok()
+    {
+        return Err(format!("alias {} shadows the built-in port name", alias));
+    }
+
+    check_keyword(alias)
+}
+
+/// Refuses aliases a command would read as something other than a port:
+/// the `follow` and `arc:` audio sources of `audio set` and the EDID presets
+/// of `edid set`.
+pub fn check_keyword(alias: &str) -> Result<(), String> {
+    if alias.eq_ignore_ascii_case("follow") {
+        return Err(format!("alias {} is reserved for the follow audio source", alias));
+    }
+    if alias.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("arc:")) {
+        return Err(format!("alias {} can't start with arc:, which names ARC audio", alias));
+    }
+    if alias.parse::<EdidPreset>().is_ok() {
+        return Err(format!("alias {} is the name of an EDID preset", alias));
+    }
+
+    Ok(())
+}
+
+// Derived `Deserialize` impls can't be handed any state, so the state the
+// checks below need is kept per thread for the duration of a read.
+thread_local! {
+    /// Aliases read so far in the `input`, `output` or `audio` section being
+    /// deserialized, `None` outside of one.
+    static SECTION_ALIASES: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    /// Cleared by [`leniently`].
+    static STRICT: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Runs `read` without refusing repeated keys, repeated aliases and aliases
+/// of built-in port names, for `config validate` to report them along with
+/// every other problem. The last of several equal keys wins.
+pub fn leniently<T>(read: impl FnOnce() -> T) -> T {
+    let strict = STRICT.with(|strict| strict.replace(false));
+    let _restore = Restore(Some(move || STRICT.with(|cell| cell.set(strict))));
+    read()
+}
+
+/// Runs its function when dropped, so thread-local state is put back even if
+/// reading panics.
+struct Restore<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Drop for Restore<F> {
+    fn drop(&mut self) {
+        if let Some(restore) = self.0.take() {
+            restore();
+        }
+    }
+}
 
-    return Ok(configuration);
+/// Reads a section with aliases, refusing an alias used both in its flat
+/// `aliases` map and under `ports`.
+fn alias_section<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let outer = SECTION_ALIASES.with(|aliases| aliases.replace(Some(Vec::new())));
+    let restore = move || SECTION_ALIASES.with(|aliases| *aliases.borrow_mut() = outer);
+    let _restore = Restore(Some(restore));
+    T::deserialize(deserializer)
+}
+
+/// [`check_alias`], also refusing an alias already read in the same section.
+fn claim_alias(alias: &str) -> Result<(), String> {
+    check_alias(alias)?;
+    SECTION_ALIASES.with(|aliases| match aliases.borrow_mut().as_mut() {
+        Some(aliases) if aliases.iter().any(|seen| seen == alias) => {
+            Err(format!("{} is defined more than once", alias))
+        }
+        Some(aliases) => {
+            aliases.push(alias.to_string());
+            Ok(())
+        }
+        None => Ok(()),
+    })
+}
+
+// Duplicate and shadowing keys are refused from `visit_str` while the key is
+// read, serde_yaml only reports the position of the key for errors raised
+// there rather than the position of the whole map.
+
+/// Reads one key of a map or item of a list, refusing names already in `seen`
+/// and names that fail `check`.
+struct UniqueName<'a> {
+    seen: &'a [String],
+    check: fn(&str) -> Result<(), String>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for UniqueName<'a> {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for UniqueName<'a> {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a name")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<String, E> {
+        if !STRICT.with(Cell::get) {
+            return Ok(name.to_string());
+        }
+        if self.seen.iter().any(|seen| seen == name) {
+            return Err(E::custom(format!("{} is defined more than once", name)));
+        }
+        (self.check)(name).map_err(E::custom)?;
+
+        Ok(name.to_string())
+    }
+}
+
+struct UniqueMap<K, V> {
+    check: fn(&str) -> Result<(), String>,
+    marker: PhantomData<(K, V)>,
+}
+
+impl<'de, K, V> Visitor<'de> for UniqueMap<K, V>
+where
+    K: FromStr + Hash + Eq,
+    K::Err: fmt::Display,
+    V: Deserialize<'de>,
+{
+    type Value = IndexMap<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+        let mut names: Vec<String> = Vec::new();
+        let mut map = IndexMap::new();
+        while let Some(name) = access.next_key_seed(UniqueName {
+            seen: &names,
+            check: self.check,
+        })? {
+            let key = name.parse::<K>().map_err(de::Error::custom)?;
+            map.insert(key, access.next_value()?);
+            names.push(name);
+        }
+
+        Ok(map)
+    }
+}
+
+/// Map in file order that refuses duplicate keys.
+fn unique_keys<'de, D, K, V>(deserializer: D) -> Result<IndexMap<K, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: FromStr + Hash + Eq,
+    K::Err: fmt::Display,
+    V: Deserialize<'de>,
+{
+    deserializer.deserialize_map(UniqueMap {
+        check: |_name| Ok(()),
+        marker: PhantomData,
+    })
+}
+
+/// Alias map in file order that refuses duplicate aliases and aliases of
+/// built-in port names, see [`claim_alias`].
+fn alias_keys<'de, D, V>(deserializer: D) -> Result<IndexMap<String, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    deserializer.deserialize_map(UniqueMap {
+        check: claim_alias,
+        marker: PhantomData,
+    })
+}
+
+struct AliasList;
+
+impl<'de> Visitor<'de> for AliasList {
+    type Value = Vec<String>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of aliases")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Vec<String>, S::Error> {
+        let mut aliases: Vec<String> = Vec::new();
+        while let Some(alias) = access.next_element_seed(UniqueName {
+            seen: &aliases,
+            check: claim_alias,
+        })? {
+            aliases.push(alias);
+        }
+
+        Ok(aliases)
+    }
+}
+
+/// Alias list of a port, with the same checks as [`alias_keys`].
+fn alias_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    deserializer.deserialize_seq(AliasList)
+}
+
+/// Checks that this build understands configuration schema `version`.
+pub fn check_version(version: u32) -> Result<()> {
+    if version == 0 || version > CONFIGURATION_VERSION {
+        return Err(HdmiSwitchError::InvalidConfiguration(format!(
+            "version {} is not supported, this hdmi-switch reads version {}",
+            version, CONFIGURATION_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks that `timeout` is a usable number of seconds.
+pub fn check_timeout(timeout: f64) -> Result<f64> {
+    if !timeout.is_finite() || timeout <= 0.0 {
+        return Err(HdmiSwitchError::InvalidConfiguration(format!(
+            "timeout must be a positive number of seconds, got {}",
+            timeout
+        )));
+    }
+
+    Ok(timeout)
+}
+
+/// Reads and checks the configuration file at `file_path`.
+pub fn get_configuration(file_path: String) -> Result<Configuration> {
+    let configuration = read_configuration(&file_path)?;
+    configuration.check()?;
+
+    Ok(configuration)
+}
+
+/// Parses the configuration file at `file_path` without checking its values,
+/// see [`crate::validation::validate`].
+pub fn read_configuration(file_path: &str) -> Result<Configuration> {
+    let contents = fs::read_to_string(file_path).map_err(|source| {
+        HdmiSwitchError::ConfigNotFound {
+            path: file_path.to_string(),
+            source,
+        }
+    })?;
+
+    serde_yaml::from_str(contents.as_str()).map_err(|err| parse_error(file_path, &err))
+}
+
+/// Error for a configuration file at `path` that couldn't be parsed.
+pub(crate) fn parse_error(path: &str, err: &serde_yaml::Error) -> HdmiSwitchError {
+    HdmiSwitchError::ConfigParse {
+        path: path.to_string(),
+        message: suggest_field(&err.to_string()),
+    }
+}
+
+/// Replaces the list of expected fields in serde's "unknown field `hots`,
+/// expected one of `host`, `port`, ..." with the closest one, e.g. "did you
+/// mean `host`?". Other messages are returned unchanged.
+fn suggest_field(message: &str) -> String {
+    let Some(start) = message.find("unknown field `") else {
+        return message.to_string();
+    };
+    let name_start = start + "unknown field `".len();
+    let Some(name_length) = message[name_start..].find('`') else {
+        return message.to_string();
+    };
+    let name = &message[name_start..name_start + name_length];
+    let rest = &message[name_start + name_length + 1..];
+    let Some(expected) = rest.strip_prefix(", expected ") else {
+        return message.to_string();
+    };
+    let (expected, location) = match expected.find(" at line ") {
+        Some(position) => expected.split_at(position),
+        None => (expected, ""),
+    };
+
+    let closest = expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|field| (edit_distance(name, field), field))
+        .min();
+    match closest {
+        Some((distance, field)) if distance <= 2 && distance < name.len() => format!(
+            "{}unknown field `{}`, did you mean `{}`?{}",
+            &message[..start],
+            name,
+            field,
+            location
+        ),
+        _ => message.to_string(),
+    }
+}
+
+/// Number of single character insertions, deletions and substitutions
+/// turning `from` into `to`.
+fn edit_distance(from: &str, to: &str) -> usize {
+    let to: Vec<char> = to.chars().collect();
+    let mut previous: Vec<usize> = (0..=to.len()).collect();
+    for (row, from_char) in from.chars().enumerate() {
+        let mut current = vec![row + 1];
+        for (column, to_char) in to.iter().enumerate() {
+            let substitution = previous[column] + usize::from(from_char != *to_char);
+            current.push(substitution.min(previous[column + 1] + 1).min(current[column] + 1));
+        }
+        previous = current;
+    }
+
+    previous[to.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("host", "host"), 0);
+        assert_eq!(edit_distance("hots", "host"), 2);
+        assert_eq!(edit_distance("alias", "aliases"), 2);
+        assert_eq!(edit_distance("prot", "port"), 2);
+        assert_eq!(edit_distance("", "port"), 4);
+        assert_eq!(edit_distance("model", ""), 5);
+    }
+
+    // Built from serde_yaml's own message so a change in its wording fails here.
+    #[test]
+    fn unknown_field_gets_the_closest_one() {
+        let err = serde_yaml::from_str::<ServerConfiguration>("host: a\nprot: 23\n").unwrap_err();
+
+        let message = suggest_field(&err.to_string());
+
+        assert!(message.contains("unknown field `prot`, did you mean `port`?"), "{}", message);
+        assert!(message.contains("at line 2 column 1"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_field_far_from_every_field_is_left_alone() {
+        let err = serde_yaml::from_str::<ServerConfiguration>("address: a\n").unwrap_err();
+
+        assert_eq!(suggest_field(&err.to_string()), err.to_string());
+    }
+
+    #[test]
+    fn strict_reading_is_restored_after_a_panic() {
+        let repeated = "input:\n  aliases:\n    pc: hdmiin1\n    pc: hdmiin2\n";
+
+        let panicked = std::panic::catch_unwind(|| leniently(|| panic!("reading failed")));
+
+        assert!(panicked.is_err());
+        assert!(serde_yaml::from_str::<Configuration>(repeated).is_err());
+        assert!(leniently(|| serde_yaml::from_str::<Configuration>(repeated)).is_ok());
+    }
+
+    #[test]
+    fn other_messages_are_left_alone() {
+        assert_eq!(suggest_field("invalid type: string"), "invalid type: string");
+        assert_eq!(suggest_field("unknown field `x"), "unknown field `x");
+    }
 }
@@ -0,0 +1,499 @@
+use crate::audio::{AudioSource, AudioTarget, ARC_PREFIX, FOLLOW};
+use crate::cec::CecCommand;
+use crate::edid::{Edid, EdidPreset};
+use crate::error::{HdmiSwitchError, Result};
+use crate::port::{AudioOutput, Input, Output};
+use serde::{Deserialize, Serialize};
+
+/// Everything that differs between switch models: which ports exist, what the
+/// API calls them and the syntax of commands and their responses.
+///
+/// Ports are the [`Input`], [`Output`] and [`AudioOutput`] enums, which have
+/// four variants each. A profile can use fewer of them and rename them, but
+/// larger switches such as 8x8 matrices can't be described yet.
+pub trait DeviceProfile {
+    /// Model name as printed on the device.
+    fn model(&self) -> &'static str;
+
+    /// Physical inputs of the device.
+    fn inputs(&self) -> &'static [Input];
+
+    /// Physical outputs of the device, not including [`Output::All`].
+    fn outputs(&self) -> &'static [Output];
+
+    /// Whether a single command can route an input to every output.
+    fn supports_all_outputs(&self) -> bool;
+
+    /// Name the device API uses for `input`.
+    fn input_name(&self, input: Input) -> String;
+
+    /// Name the device API uses for `output`.
+    fn output_name(&self, output: Output) -> String;
+
+    /// Command routing `input` to `output`, including its line terminator.
+    fn route_command(&self, input: Input, output: Output) -> String;
+
+    /// Checks the device reply to [`DeviceProfile::route_command`]. A reply
+    /// that is not an acknowledgement is returned as the error.
+    fn verify_route_response(&self, input: Input, output: Output, response: &str) -> Result<()>;
+
+    /// Query for the input currently routed to `output`.
+    fn routing_query(&self, output: Output) -> String;
+
+    /// Parses the reply to [`DeviceProfile::routing_query`] into
+    /// `(input, output)`.
+    fn parse_routing_response(&self, response: &str) -> Result<(Input, Output)>;
+
+    /// EDID presets built into the device.
+    fn edid_presets(&self) -> &'static [EdidPreset];
+
+    /// Command making `input` advertise `edid`, including its line terminator.
+    fn edid_command(&self, input: Input, edid: Edid) -> String;
+
+    /// Checks the device reply to [`DeviceProfile::edid_command`].
+    fn verify_edid_response(&self, input: Input, edid: Edid, response: &str) -> Result<()>;
+
+    /// Query for the EDID `input` advertises.
+    fn edid_query(&self, input: Input) -> String;
+
+    /// Parses the reply to [`DeviceProfile::edid_query`] into
+    /// `(input, edid)`.
+    fn parse_edid_response(&self, response: &str) -> Result<(Input, Edid)>;
+
+    /// Command sending `command` to the display on `output` over HDMI-CEC,
+    /// including its line terminator.
+    fn cec_command(&self, output: Output, command: CecCommand) -> String;
+
+    /// Checks the device reply to [`DeviceProfile::cec_command`].
+    fn verify_cec_response(
+        &self,
+        output: Output,
+        command: CecCommand,
+        response: &str,
+    ) -> Result<()>;
+
+    /// Analog audio outputs of the device.
+    fn audio_outputs(&self) -> &'static [AudioOutput];
+
+    /// Command making `target` play `source`, including its line terminator.
+    fn audio_command(&self, target: AudioTarget, source: AudioSource) -> String;
+
+    /// Checks the device reply to [`DeviceProfile::audio_command`].
+    fn verify_audio_response(
+        &self,
+        target: AudioTarget,
+        source: AudioSource,
+        response: &str,
+    ) -> Result<()>;
+
+    /// Query for the audio `target` plays.
+    fn audio_query(&self, target: AudioTarget) -> String;
+
+    /// Parses the reply to [`DeviceProfile::audio_query`] into
+    /// `(target, source)`.
+    fn parse_audio_response(&self, response: &str) -> Result<(AudioTarget, AudioSource)>;
+
+    /// Command muting or unmuting `target`, including its line terminator.
+    fn mute_command(&self, target: AudioTarget, muted: bool) -> String;
+
+    /// Checks the device reply to [`DeviceProfile::mute_command`].
+    fn verify_mute_response(&self, target: AudioTarget, muted: bool, response: &str) -> Result<()>;
+
+    /// Query for whether `target` is muted.
+    fn mute_query(&self, target: AudioTarget) -> String;
+
+    /// Parses the reply to [`DeviceProfile::mute_query`] into
+    /// `(target, muted)`.
+    fn parse_mute_response(&self, response: &str) -> Result<(AudioTarget, bool)>;
+
+    /// Command turning the audio return channel of `output` on or off,
+    /// including its line terminator.
+    fn arc_command(&self, output: Output, on: bool) -> String;
+
+    /// Checks the device reply to [`DeviceProfile::arc_command`].
+    fn verify_arc_response(&self, output: Output, on: bool, response: &str) -> Result<()>;
+
+    /// Query for whether the audio return channel of `output` is on.
+    fn arc_query(&self, output: Output) -> String;
+
+    /// Parses the reply to [`DeviceProfile::arc_query`] into `(output, on)`.
+    fn parse_arc_response(&self, response: &str) -> Result<(Output, bool)>;
+
+    fn has_input(&self, input: Input) -> bool {
+        self.inputs().contains(&input)
+    }
+
+    fn has_output(&self, output: Output) -> bool {
+        match output {
+            Output::All => self.supports_all_outputs(),
+            output => self.outputs().contains(&output),
+        }
+    }
+
+    fn has_audio_output(&self, output: AudioOutput) -> bool {
+        self.audio_outputs().contains(&output)
+    }
+
+    /// The input [`DeviceProfile::input_name`] calls `name`.
+    fn parse_input(&self, name: &str) -> Option<Input> {
+        self.inputs()
+            .iter()
+            .copied()
+            .find(|input| self.input_name(*input).eq_ignore_ascii_case(name))
+    }
+
+    /// The output [`DeviceProfile::output_name`] calls `name`, including
+    /// [`Output::All`] if the device supports it.
+    fn parse_output(&self, name: &str) -> Option<Output> {
+        self.outputs()
+            .iter()
+            .copied()
+            .chain(self.supports_all_outputs().then_some(Output::All))
+            .find(|output| self.output_name(*output).eq_ignore_ascii_case(name))
+    }
+}
+
+/// Switch models with a [`DeviceProfile`], selected with `server.model` in the
+/// configuration file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Model {
+    #[default]
+    #[serde(rename = "4KMX44-H2", alias = "4kmx44-h2")]
+    Av4kmx44H2,
+}
+
+impl Model {
+    pub fn profile(&self) -> Box<dyn DeviceProfile> {
+        match self {
+            Model::Av4kmx44H2 => Box::new(Av4kmx44H2),
+        }
+    }
+}
+
+/// AV Access 4KMX44-H2, a 4x4 matrix controlled over telnet.
+pub struct Av4kmx44H2;
+
+impl DeviceProfile for Av4kmx44H2 {
+    fn model(&self) -> &'static str {
+        "4KMX44-H2"
+    }
+
+    fn inputs(&self) -> &'static [Input] {
+        &Input::PORTS
+    }
+
+    fn outputs(&self) -> &'static [Output] {
+        &Output::PORTS
+    }
+
+    fn supports_all_outputs(&self) -> bool {
+        true
+    }
+
+    fn input_name(&self, input: Input) -> String {
+        input.to_string()
+    }
+
+    fn output_name(&self, output: Output) -> String {
+        output.to_string()
+    }
+
+    fn route_command(&self, input: Input, output: Output) -> String {
+        format!("SET SW {} {}\n\r", self.input_name(input), self.output_name(output))
+    }
+
+    /// `SET SW hdmiin1 hdmiout2` is acknowledged by echoing it back without
+    /// the `SET` keyword, i.e. `SW hdmiin1 hdmiout2`.
+    fn verify_route_response(&self, input: Input, output: Output, response: &str) -> Result<()> {
+        let expected = format!("SW {} {}", self.input_name(input), self.output_name(output));
+        verify_echo(&expected, response)
+    }
+
+    fn routing_query(&self, output: Output) -> String {
+        format!("GET SW {}\n\r", self.output_name(output))
+    }
+
+    /// `GET SW` is answered in the same format `SET SW` is acknowledged with,
+    /// e.g. `SW hdmiin3 hdmiout1`.
+    fn parse_routing_response(&self, response: &str) -> Result<(Input, Output)> {
+        let response = response.trim();
+        let fields: Vec<&str> = response.split_whitespace().collect();
+        match fields.as_slice() {
+            [keyword, input, output] if keyword.eq_ignore_ascii_case("SW") => {
+                match (self.parse_input(input), self.parse_output(output)) {
+                    (Some(input), Some(output)) => Ok((input, output)),
+                    _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+                }
+            }
+            _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+        }
+    }
+
+    fn edid_presets(&self) -> &'static [EdidPreset] {
+        &EdidPreset::ALL
+    }
+
+    fn edid_command(&self, input: Input, edid: Edid) -> String {
+        format!("SET EDID {} {}\n\r", self.input_name(input), edid)
+    }
+
+    /// Acknowledged like routes, e.g. `EDID hdmiin1 4k60hz_2ch`.
+    fn verify_edid_response(&self, input: Input, edid: Edid, response: &str) -> Result<()> {
+        verify_echo(&format!("EDID {} {}", self.input_name(input), edid), response)
+    }
+
+    fn edid_query(&self, input: Input) -> String {
+        format!("GET EDID {}\n\r", self.input_name(input))
+    }
+
+    /// Answered in the format `SET EDID` is acknowledged with.
+    fn parse_edid_response(&self, response: &str) -> Result<(Input, Edid)> {
+        let response = response.trim();
+        let fields: Vec<&str> = response.split_whitespace().collect();
+        match fields.as_slice() {
+            [keyword, input, edid] if keyword.eq_ignore_ascii_case("EDID") => {
+                match (self.parse_input(input), edid.parse::<Edid>()) {
+                    (Some(input), Ok(edid)) => Ok((input, edid)),
+                    _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+                }
+            }
+            _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+        }
+    }
+
+    fn cec_command(&self, output: Output, command: CecCommand) -> String {
+        format!("SET CEC {} {}\n\r", self.output_name(output), command)
+    }
+
+    /// Acknowledged like routes, e.g. `CEC hdmiout1 on`.
+    fn verify_cec_response(
+        &self,
+        output: Output,
+        command: CecCommand,
+        response: &str,
+    ) -> Result<()> {
+        verify_echo(&format!("CEC {} {}", self.output_name(output), command), response)
+    }
+
+    fn audio_outputs(&self) -> &'static [AudioOutput] {
+        &AudioOutput::PORTS
+    }
+
+    fn audio_command(&self, target: AudioTarget, source: AudioSource) -> String {
+        format!("SET AUDIO {} {}\n\r", self.target_name(target), self.source_name(source))
+    }
+
+    /// Acknowledged like routes, e.g. `AUDIO audioout1 hdmiin2`.
+    fn verify_audio_response(
+        &self,
+        target: AudioTarget,
+        source: AudioSource,
+        response: &str,
+    ) -> Result<()> {
+        let expected = format!("AUDIO {} {}", self.target_name(target), self.source_name(source));
+        verify_echo(&expected, response)
+    }
+
+    fn audio_query(&self, target: AudioTarget) -> String {
+        format!("GET AUDIO {}\n\r", self.target_name(target))
+    }
+
+    /// Answered in the format `SET AUDIO` is acknowledged with.
+    fn parse_audio_response(&self, response: &str) -> Result<(AudioTarget, AudioSource)> {
+        let response = response.trim();
+        let fields: Vec<&str> = response.split_whitespace().collect();
+        match fields.as_slice() {
+            [keyword, target, source] if keyword.eq_ignore_ascii_case("AUDIO") => {
+                match (self.parse_target(target), self.parse_source(source)) {
+                    (Some(target), Some(source)) => Ok((target, source)),
+                    _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+                }
+            }
+            _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+        }
+    }
+
+    fn mute_command(&self, target: AudioTarget, muted: bool) -> String {
+        format!("SET MUTE {} {}\n\r", self.target_name(target), on_off(muted))
+    }
+
+    /// Acknowledged like routes, e.g. `MUTE hdmiout1 on`.
+    fn verify_mute_response(&self, target: AudioTarget, muted: bool, response: &str) -> Result<()> {
+        verify_echo(&format!("MUTE {} {}", self.target_name(target), on_off(muted)), response)
+    }
+
+    fn mute_query(&self, target: AudioTarget) -> String {
+        format!("GET MUTE {}\n\r", self.target_name(target))
+    }
+
+    /// Answered in the format `SET MUTE` is acknowledged with.
+    fn parse_mute_response(&self, response: &str) -> Result<(AudioTarget, bool)> {
+        let response = response.trim();
+        let fields: Vec<&str> = response.split_whitespace().collect();
+        match fields.as_slice() {
+            [keyword, target, state] if keyword.eq_ignore_ascii_case("MUTE") => {
+                match (self.parse_target(target), parse_on_off(state)) {
+                    (Some(target), Some(muted)) => Ok((target, muted)),
+                    _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+                }
+            }
+            _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+        }
+    }
+
+    fn arc_command(&self, output: Output, on: bool) -> String {
+        format!("SET ARC {} {}\n\r", self.output_name(output), on_off(on))
+    }
+
+    /// Acknowledged like routes, e.g. `ARC hdmiout4 on`.
+    fn verify_arc_response(&self, output: Output, on: bool, response: &str) -> Result<()> {
+        verify_echo(&format!("ARC {} {}", self.output_name(output), on_off(on)), response)
+    }
+
+    fn arc_query(&self, output: Output) -> String {
+        format!("GET ARC {}\n\r", self.output_name(output))
+    }
+
+    /// Answered in the format `SET ARC` is acknowledged with.
+    fn parse_arc_response(&self, response: &str) -> Result<(Output, bool)> {
+        let response = response.trim();
+        let fields: Vec<&str> = response.split_whitespace().collect();
+        match fields.as_slice() {
+            [keyword, output, state] if keyword.eq_ignore_ascii_case("ARC") => {
+                match (self.parse_output(output), parse_on_off(state)) {
+                    (Some(Output::All), _) | (None, _) | (_, None) => {
+                        Err(HdmiSwitchError::UnexpectedResponse(response.to_string()))
+                    }
+                    (Some(output), Some(on)) => Ok((output, on)),
+                }
+            }
+            _ => Err(HdmiSwitchError::UnexpectedResponse(response.to_string())),
+        }
+    }
+}
+
+impl Av4kmx44H2 {
+    /// Name the device API uses for `target`.
+    fn target_name(&self, target: AudioTarget) -> String {
+        match target {
+            AudioTarget::Hdmi(output) => self.output_name(output),
+            AudioTarget::Analog(output) => output.to_string(),
+        }
+    }
+
+    /// Name the device API uses for `source`, e.g. `arc_hdmiout4`.
+    fn source_name(&self, source: AudioSource) -> String {
+        match source {
+            AudioSource::Follow => FOLLOW.to_string(),
+            AudioSource::Input(input) => self.input_name(input),
+            AudioSource::Arc(output) => format!("{}{}", ARC_PREFIX, self.output_name(output)),
+        }
+    }
+
+    /// The target [`Av4kmx44H2::target_name`] calls `name`.
+    fn parse_target(&self, name: &str) -> Option<AudioTarget> {
+        match self.parse_output(name) {
+            Some(Output::All) => None,
+            Some(output) => Some(AudioTarget::Hdmi(output)),
+            None => name.parse().ok().map(AudioTarget::Analog),
+        }
+    }
+
+    /// The source [`Av4kmx44H2::source_name`] calls `name`.
+    fn parse_source(&self, name: &str) -> Option<AudioSource> {
+        if name.eq_ignore_ascii_case(FOLLOW) {
+            return Some(AudioSource::Follow);
+        }
+
+        let arc = name
+            .get(..ARC_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(ARC_PREFIX))
+            .map(|_prefix| &name[ARC_PREFIX.len()..]);
+        match arc {
+            Some(output) => match self.parse_output(output)? {
+                Output::All => None,
+                output => Some(AudioSource::Arc(output)),
+            },
+            None => self.parse_input(name).map(AudioSource::Input),
+        }
+    }
+}
+
+/// The API spelling of a switch state.
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn parse_on_off(state: &str) -> Option<bool> {
+    match state {
+        state if state.eq_ignore_ascii_case("on") => Some(true),
+        state if state.eq_ignore_ascii_case("off") => Some(false),
+        _ => None,
+    }
+}
+
+/// Checks the acknowledgement of a `SET` command, which the device echoes back
+/// without the `SET` keyword. Anything else is returned as the error.
+fn verify_echo(expected: &str, response: &str) -> Result<()> {
+    let response = response.trim();
+
+    if response.eq_ignore_ascii_case(expected) {
+        return Ok(());
+    }
+
+    Err(HdmiSwitchError::DeviceRejected {
+        command: format!("SET {}", expected),
+        response: response.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_acknowledgement_is_the_command_without_set() {
+        let profile = Av4kmx44H2;
+
+        assert!(profile
+            .verify_route_response(Input::Hdmi1, Output::Hdmi2, "SW hdmiin1 hdmiout2\r")
+            .is_ok());
+        assert!(profile
+            .verify_route_response(Input::Hdmi1, Output::Hdmi2, "sw HDMIIN1 HDMIOUT2")
+            .is_ok());
+    }
+
+    #[test]
+    fn other_replies_are_rejections() {
+        let profile = Av4kmx44H2;
+
+        for response in ["Command FAILED", "SW hdmiin1 hdmiout3", "Welcome to 4KMX44-H2"] {
+            match profile.verify_route_response(Input::Hdmi1, Output::Hdmi2, response) {
+                Err(HdmiSwitchError::DeviceRejected { command, response: reply }) => {
+                    assert_eq!(command, "SET SW hdmiin1 hdmiout2");
+                    assert_eq!(reply, response);
+                }
+                result => panic!("{} was accepted as {:?}", response, result),
+            }
+        }
+    }
+
+    #[test]
+    fn responses_are_parsed_with_the_api_names() {
+        let profile = Av4kmx44H2;
+
+        assert_eq!(
+            profile.parse_routing_response("SW HDMIIN3 all").unwrap(),
+            (Input::Hdmi3, Output::All)
+        );
+        assert_eq!(
+            profile.parse_audio_response("AUDIO audioout2 ARC_hdmiout4").unwrap(),
+            (AudioTarget::Analog(AudioOutput::Analog2), AudioSource::Arc(Output::Hdmi4))
+        );
+        assert!(profile.parse_routing_response("SW hdmiin5 hdmiout1").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::error::{HdmiSwitchError, Result};
+use crate::port::{Input, Output};
+use crate::table::{Column, Table};
+use crate::utils::display_port;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// EDID presets built into the switch. The API names give the resolution,
+/// whether 3D or HDR is advertised and the number of audio channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdidPreset {
+    Fhd2Ch,
+    Fhd6Ch,
+    Fhd8Ch,
+    Fhd3d2Ch,
+    Fhd3d6Ch,
+    Fhd3d8Ch,
+    Uhd30Hz2Ch,
+    Uhd30Hz6Ch,
+    Uhd30Hz8Ch,
+    Uhd60Hz2Ch,
+    Uhd60Hz6Ch,
+    Uhd60Hz8Ch,
+    Uhd60HzHdr2Ch,
+    Uhd60HzHdr6Ch,
+    Uhd60HzHdr8Ch,
+}
+
+impl EdidPreset {
+    pub const ALL: [EdidPreset; 15] = [
+        EdidPreset::Fhd2Ch,
+        EdidPreset::Fhd6Ch,
+        EdidPreset::Fhd8Ch,
+        EdidPreset::Fhd3d2Ch,
+        EdidPreset::Fhd3d6Ch,
+        EdidPreset::Fhd3d8Ch,
+        EdidPreset::Uhd30Hz2Ch,
+        EdidPreset::Uhd30Hz6Ch,
+        EdidPreset::Uhd30Hz8Ch,
+        EdidPreset::Uhd60Hz2Ch,
+        EdidPreset::Uhd60Hz6Ch,
+        EdidPreset::Uhd60Hz8Ch,
+        EdidPreset::Uhd60HzHdr2Ch,
+        EdidPreset::Uhd60HzHdr6Ch,
+        EdidPreset::Uhd60HzHdr8Ch,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdidPreset::Fhd2Ch => "1080p_2ch",
+            EdidPreset::Fhd6Ch => "1080p_6ch",
+            EdidPreset::Fhd8Ch => "1080p_8ch",
+            EdidPreset::Fhd3d2Ch => "1080p_3d_2ch",
+            EdidPreset::Fhd3d6Ch => "1080p_3d_6ch",
+            EdidPreset::Fhd3d8Ch => "1080p_3d_8ch",
+            EdidPreset::Uhd30Hz2Ch => "4k30hz_2ch",
+            EdidPreset::Uhd30Hz6Ch => "4k30hz_6ch",
+            EdidPreset::Uhd30Hz8Ch => "4k30hz_8ch",
+            EdidPreset::Uhd60Hz2Ch => "4k60hz_2ch",
+            EdidPreset::Uhd60Hz6Ch => "4k60hz_6ch",
+            EdidPreset::Uhd60Hz8Ch => "4k60hz_8ch",
+            EdidPreset::Uhd60HzHdr2Ch => "4k60hz_hdr_2ch",
+            EdidPreset::Uhd60HzHdr6Ch => "4k60hz_hdr_6ch",
+            EdidPreset::Uhd60HzHdr8Ch => "4k60hz_hdr_8ch",
+        }
+    }
+
+    /// What the preset advertises, for people to read.
+    pub fn description(&self) -> &'static str {
+        match self {
+            EdidPreset::Fhd2Ch => "1920x1080p60, 2 channel audio",
+            EdidPreset::Fhd6Ch => "1920x1080p60, 5.1 channel audio",
+            EdidPreset::Fhd8Ch => "1920x1080p60, 7.1 channel audio",
+            EdidPreset::Fhd3d2Ch => "1920x1080p60 with 3D, 2 channel audio",
+            EdidPreset::Fhd3d6Ch => "1920x1080p60 with 3D, 5.1 channel audio",
+            EdidPreset::Fhd3d8Ch => "1920x1080p60 with 3D, 7.1 channel audio",
+            EdidPreset::Uhd30Hz2Ch => "3840x2160p30, 2 channel audio",
+            EdidPreset::Uhd30Hz6Ch => "3840x2160p30, 5.1 channel audio",
+            EdidPreset::Uhd30Hz8Ch => "3840x2160p30, 7.1 channel audio",
+            EdidPreset::Uhd60Hz2Ch => "3840x2160p60, 2 channel audio",
+            EdidPreset::Uhd60Hz6Ch => "3840x2160p60, 5.1 channel audio",
+            EdidPreset::Uhd60Hz8Ch => "3840x2160p60, 7.1 channel audio",
+            EdidPreset::Uhd60HzHdr2Ch => "3840x2160p60 with HDR, 2 channel audio",
+            EdidPreset::Uhd60HzHdr6Ch => "3840x2160p60 with HDR, 5.1 channel audio",
+            EdidPreset::Uhd60HzHdr8Ch => "3840x2160p60 with HDR, 7.1 channel audio",
+        }
+    }
+}
+
+impl fmt::Display for EdidPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EdidPreset {
+    type Err = HdmiSwitchError;
+
+    fn from_str(preset: &str) -> Result<Self> {
+        match EdidPreset::ALL
+            .iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(preset))
+        {
+            Some(known) => Ok(*known),
+            None => Err(HdmiSwitchError::UnknownEdid(preset.to_string())),
+        }
+    }
+}
+
+impl Serialize for EdidPreset {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The EDID an input advertises to its source: a built-in preset or the one
+/// read from the sink on an output. The API name of a copy is e.g.
+/// `copy_hdmiout2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edid {
+    Preset(EdidPreset),
+    CopyFrom(Output),
+}
+
+const COPY_PREFIX: &str = "copy_";
+
+impl fmt::Display for Edid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edid::Preset(preset) => write!(f, "{}", preset),
+            Edid::CopyFrom(output) => write!(f, "{}{}", COPY_PREFIX, output),
+        }
+    }
+}
+
+/// Parses the API names, see [`crate::utils::Switch::resolve_edid`] for the
+/// names accepted on the command line.
+impl FromStr for Edid {
+    type Err = HdmiSwitchError;
+
+    fn from_str(edid: &str) -> Result<Self> {
+        let copy = edid
+            .get(..COPY_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(COPY_PREFIX))
+            .map(|_prefix| &edid[COPY_PREFIX.len()..]);
+        match copy {
+            Some(output) => match output.parse::<Output>() {
+                Ok(Output::All) | Err(_) => Err(HdmiSwitchError::UnknownEdid(edid.to_string())),
+                Ok(output) => Ok(Edid::CopyFrom(output)),
+            },
+            None => Ok(Edid::Preset(edid.parse()?)),
+        }
+    }
+}
+
+impl Serialize for Edid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Serializable EDID of each input, as printed by `edid get`.
+#[derive(Serialize, Debug)]
+pub struct EdidView {
+    pub edid: Vec<InputEdidView>,
+}
+
+/// An input along with its configured name and aliases and its EDID.
+#[derive(Serialize, Debug)]
+pub struct InputEdidView {
+    pub input: Input,
+    pub input_name: Option<String>,
+    pub input_aliases: Vec<String>,
+    pub edid: Edid,
+    pub edid_display: String,
+}
+
+impl EdidView {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new()]);
+
+        table.heading(0, "EDID:");
+        for input in self.edid.iter() {
+            table.row(
+                2,
+                vec![
+                    format!(
+                        "{}:",
+                        display_port(
+                            input.input.as_str(),
+                            input.input_name.as_deref(),
+                            &input.input_aliases
+                        )
+                    ),
+                    input.edid_display.clone(),
+                ],
+            );
+        }
+
+        table
+    }
+}
+
+/// Serializable list of presets, as printed by `edid list-presets`.
+#[derive(Serialize, Debug)]
+pub struct PresetsView {
+    pub presets: Vec<PresetView>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PresetView {
+    pub name: EdidPreset,
+    pub description: String,
+}
+
+impl PresetsView {
+    pub fn new(presets: &[EdidPreset]) -> Self {
+        PresetsView {
+            presets: presets
+                .iter()
+                .map(|preset| PresetView {
+                    name: *preset,
+                    description: preset.description().to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new()]);
+
+        table.heading(0, "EDID presets:");
+        for preset in self.presets.iter() {
+            table.row(2, vec![format!("{}:", preset.name), preset.description.clone()]);
+        }
+
+        table
+    }
+}
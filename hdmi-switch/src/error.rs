@@ -0,0 +1,125 @@
+use std::io;
+use std::time::Duration;
+use thiserror::Error;
+
+pub type Result<T, E = HdmiSwitchError> = std::result::Result<T, E>;
+
+/// Every failure the library and CLI report. Each variant maps to its own
+/// process exit code so wrapper scripts can tell them apart.
+#[derive(Debug, Error)]
+pub enum HdmiSwitchError {
+    #[error("{0}")]
+    Usage(String),
+
+    #[error("Couldn't format output: {0}")]
+    Render(String),
+
+    #[error("Couldn't {action}")]
+    Console {
+        action: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Couldn't read configuration file {path}")]
+    ConfigNotFound {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Couldn't parse configuration file {path}: {message}")]
+    ConfigParse { path: String, message: String },
+
+    #[error("Couldn't write configuration file {path}")]
+    ConfigWrite {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("{kind} {alias} not supported")]
+    UnknownAlias { kind: PortKind, alias: String },
+
+    #[error("Scene {0} not found")]
+    UnknownScene(String),
+
+    #[error("{0}")]
+    InvalidPort(String),
+
+    #[error("{kind} {port} is disabled in the configuration")]
+    PortDisabled { kind: PortKind, port: String },
+
+    #[error("Device {0} not found")]
+    UnknownDevice(String),
+
+    #[error("EDID {0} is neither a preset nor an output, see `hdmi-switch edid list-presets`")]
+    UnknownEdid(String),
+
+    #[error("Couldn't connect to the HDMI switch at {address}")]
+    ConnectionFailed {
+        address: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Timed out after {}s {action}", .timeout.as_secs_f64())]
+    Timeout { action: String, timeout: Duration },
+
+    #[error("Lost connection to the HDMI switch: {0}")]
+    ConnectionLost(String),
+
+    #[error("HDMI switch rejected command \"{command}\": {response}")]
+    DeviceRejected { command: String, response: String },
+
+    #[error("Unexpected response from HDMI switch: {0}")]
+    UnexpectedResponse(String),
+}
+
+/// Which side of the matrix an alias or port belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortKind {
+    Input,
+    Output,
+    Audio,
+}
+
+impl std::fmt::Display for PortKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortKind::Input => f.write_str("Input"),
+            PortKind::Output => f.write_str("Output"),
+            PortKind::Audio => f.write_str("Audio output"),
+        }
+    }
+}
+
+impl HdmiSwitchError {
+    /// Exit code for the CLI. These are part of the CLI interface and must
+    /// not change once released.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HdmiSwitchError::Render(_) => 1,
+            HdmiSwitchError::Usage(_) => 2,
+            HdmiSwitchError::ConnectionFailed { .. } => 3,
+            HdmiSwitchError::Timeout { .. } => 4,
+            HdmiSwitchError::ConnectionLost(_) => 5,
+            HdmiSwitchError::DeviceRejected { .. } => 6,
+            HdmiSwitchError::UnexpectedResponse(_) => 7,
+            HdmiSwitchError::Console { .. } => 8,
+            HdmiSwitchError::ConfigNotFound { .. } => 10,
+            HdmiSwitchError::ConfigParse { .. } => 11,
+            HdmiSwitchError::InvalidConfiguration(_) => 12,
+            HdmiSwitchError::ConfigWrite { .. } => 13,
+            HdmiSwitchError::UnknownAlias { .. } => 20,
+            HdmiSwitchError::UnknownScene(_) => 21,
+            HdmiSwitchError::InvalidPort(_) => 22,
+            HdmiSwitchError::PortDisabled { .. } => 23,
+            HdmiSwitchError::UnknownDevice(_) => 24,
+            HdmiSwitchError::UnknownEdid(_) => 25,
+        }
+    }
+}
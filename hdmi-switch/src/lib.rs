@@ -0,0 +1,29 @@
+//! Client library for the AV Access 4KMX44-H2 HDMI matrix switch.
+//!
+//! [`client::SwitchClient`] holds a session with the switch over one of the
+//! [`transport`]s and exposes the routing API, [`configuration`] loads the same YAML file the
+//! `hdmi-switch` CLI uses, [`sources`] finds and merges the configuration
+//! files, [`validation`] checks them and [`utils::Switch`] resolves port
+//! aliases.
+
+pub mod audio;
+pub mod cec;
+pub mod client;
+pub mod configuration;
+pub mod device;
+pub mod edid;
+pub mod error;
+pub mod port;
+pub mod sources;
+pub mod table;
+pub mod transport;
+pub mod utils;
+pub mod validation;
+pub mod wizard;
+
+pub use client::{AppliedRoutes, Route, SwitchClient};
+pub use configuration::Configuration;
+pub use device::{DeviceProfile, Model};
+pub use error::HdmiSwitchError;
+pub use port::{Input, Output};
+pub use utils::Switch;
@@ -1,106 +1,713 @@
-mod configuration;
-mod utils;
+use hdmi_switch::cec::CecCommand;
+use hdmi_switch::configuration::{self, Configuration, RouteConfiguration};
+use hdmi_switch::edid::PresetsView;
+use hdmi_switch::error::Result;
+use hdmi_switch::sources::{self, Layer, Resolved, Source};
+use hdmi_switch::table::Style;
+use hdmi_switch::utils::{DeviceView, DevicesView};
+use hdmi_switch::validation::{self, ValidationView};
+use hdmi_switch::wizard::{self, Wizard};
+use hdmi_switch::{AppliedRoutes, HdmiSwitchError, Switch, SwitchClient};
 
+use indexmap::IndexMap;
 use std::env;
 use std::error::Error;
+use std::io::{self, Write};
+use serde::Serialize;
+use std::process;
+use std::str::FromStr;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
-use telnet::Telnet;
-use anyhow::{Result, anyhow};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "hdmi-switch", about = "Cli client for 4KMX44-H2")]
 struct Opt {
-    #[structopt(short, long, default_value = "")]
-    configuration: String,
+    /// Configuration file to use instead of looking for one
+    #[structopt(short, long)]
+    configuration: Option<String>,
+
+    /// Switch to use from the devices section, defaults to default_device
+    #[structopt(short, long)]
+    device: Option<String>,
+
+    /// Format of the output: text, json or yaml
+    #[structopt(long, default_value = "text", possible_values = &["text", "json", "yaml"])]
+    output_format: OutputFormat,
+
+    /// When to colour text output: auto, always or never
+    #[structopt(long, default_value = "auto", possible_values = &["auto", "always", "never"])]
+    color: ColorChoice,
+
+    /// Seconds to wait for the connection, overrides server.connect_timeout
+    #[structopt(long, parse(try_from_str = parse_timeout))]
+    connect_timeout: Option<f64>,
+
+    /// Seconds to wait for each response, overrides server.read_timeout
+    #[structopt(long, parse(try_from_str = parse_timeout))]
+    read_timeout: Option<f64>,
 
     #[structopt(subcommand)]
     cmd: Option<SubCommand>,
 }
 
 impl Opt {
-    fn get_file_path(&self) -> Result<String, Box<dyn Error>> {
-        let mut configuration: String = self.configuration.clone();
-        if configuration == "" {
-            let home = env::var("HOME")?;
-            configuration = format!("{}/.config/hdmi-switch/configuration.yaml", home)
+    /// The file `config init` writes: the `-c` file or the user's own.
+    fn get_file_path(&self) -> Result<String> {
+        if let Some(configuration) = &self.configuration {
+            return Ok(configuration.clone());
+        }
+
+        match sources::user_path() {
+            Some(path) => Ok(path.to_string_lossy().to_string()),
+            None => Err(HdmiSwitchError::ConfigNotFound {
+                path: "$XDG_CONFIG_HOME/hdmi-switch/configuration.yaml".to_string(),
+                source: io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "neither XDG_CONFIG_HOME nor HOME is set",
+                ),
+            }),
+        }
+    }
+
+    /// Configuration files, `HDMI_SWITCH_*` variables and flags, lowest
+    /// precedence first.
+    fn layers(&self) -> Result<Vec<Layer>> {
+        let current_directory = env::current_dir().unwrap_or_else(|_err| ".".into());
+        let mut layers: Vec<Layer> = Vec::new();
+        for source in sources::discover(self.configuration.as_deref(), &current_directory)? {
+            layers.push(Layer::file(source)?);
+        }
+        layers.extend(Layer::environment(env::vars())?);
+
+        for (flag, key, timeout) in [
+            ("--connect-timeout", "connect_timeout", self.connect_timeout),
+            ("--read-timeout", "read_timeout", self.read_timeout),
+        ] {
+            if let Some(timeout) = timeout {
+                layers.push(Layer::server(
+                    Source::CommandLine(flag.to_string()),
+                    vec![(key, timeout.into())],
+                ));
+            }
+        }
+
+        Ok(layers)
+    }
+
+    /// The config subcommands work on files that may not exist or load yet.
+    /// Validation reports every problem itself rather than stopping at the
+    /// first one like loading the configuration does.
+    fn config(&self, command: ConfigCommand) -> Result<()> {
+        match command {
+            ConfigCommand::Init { force } => self.init(&self.get_file_path()?, force),
+            ConfigCommand::Validate {} => {
+                let resolved = configuration::leniently(|| sources::resolve(self.layers()?))?;
+                self.validate(&resolved)
+            }
+            ConfigCommand::Show { resolved } => {
+                self.show(&sources::resolve(self.layers()?)?, resolved)
+            }
         }
+    }
+
+    fn validate(&self, resolved: &Resolved) -> Result<()> {
+        let names: Vec<String> = resolved
+            .layers
+            .iter()
+            .map(|layer| layer.source.to_string())
+            .collect();
+        let file_path = names.join(" + ");
+        let mut problems: Vec<validation::Problem> = Vec::new();
+        for text in resolved.layers.iter().filter_map(Layer::text) {
+            problems.extend(validation::duplicate_keys(text));
+        }
+        problems.extend(validation::validate(&resolved.configuration));
+        let view = ValidationView::new(&file_path, problems);
+
+        let style = self.color.style();
+        self.output_format
+            .print(&view, || print!("{}", view.table().render(&style)))?;
 
-        return Ok(configuration);
+        match view.problems.len() {
+            0 => Ok(()),
+            count => Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "{} problem(s) found in {}",
+                count, file_path
+            ))),
+        }
     }
 
-    fn execute(self, configuration: configuration::Configuration) -> Result<()> {
-        let port = configuration.get_port();
+    fn init(&self, file_path: &str, force: bool) -> Result<()> {
+        wizard::check_overwrite(file_path, force)?;
+
+        let stdin = io::stdin();
+        let configuration = Wizard::new(stdin.lock(), io::stdout()).run()?;
 
-        let mut telnet = Telnet::connect((configuration.server.host, port), 256)
-            .expect("Couldn't connect to the server...");
+        wizard::write_configuration(file_path, &configuration, force)?;
+        println!("Wrote {}", file_path);
 
-        let _event = telnet
-            .read()
-            .expect("Error reading connection response from HDMI switch");
+        Ok(())
+    }
+
+    fn show(&self, resolved: &Resolved, with_sources: bool) -> Result<()> {
+        let style = self.color.style();
+        if with_sources {
+            let view = resolved.view()?;
+            return self
+                .output_format
+                .print(&view, || print!("{}", view.table().render(&style)));
+        }
 
-        let mut switch = utils::Switch::new();
-        for (alias, default) in configuration.input.aliases.iter() {
-            switch.load_input_alias(&alias, &default)?;
+        let configuration = &resolved.configuration;
+        match self.output_format {
+            OutputFormat::Text => OutputFormat::Yaml.print(configuration, || {}),
+            format => format.print(configuration, || {}),
         }
-        for (alias, default) in configuration.output.aliases.iter() {
-            switch.load_output_alias(&alias, &default)?;
+    }
+
+    /// `ls` for every switch under `devices`.
+    fn list_devices(&self, resolved: &Resolved) -> Result<()> {
+        let default = match resolved.configuration.default_device_name() {
+            Ok(name) => name,
+            // Several devices and no default_device, none of them is the default.
+            Err(HdmiSwitchError::Usage(_)) => None,
+            Err(err) => return Err(err),
+        };
+        let mut devices: IndexMap<String, DeviceView> = IndexMap::new();
+        for name in resolved.configuration.device_names() {
+            let configuration = resolved.device(Some(name))?;
+            configuration.check()?;
+            let switch = Switch::from_configuration(&configuration)?;
+            devices.insert(
+                name.to_string(),
+                DeviceView {
+                    address: configuration.get_address(),
+                    default: default == Some(name),
+                    switch: switch.view(),
+                },
+            );
         }
 
-        match self.cmd {
-            Some(SubCommand::Switch(switch_opts)) => {
-                let buffer: String = switch.command_build(
-                    &switch_opts.input, 
-                    &switch_opts.output,
-                )?;
+        let view = DevicesView { devices };
+        let style = self.color.style();
+        self.output_format
+            .print(&view, || print!("{}", view.table().render(&style)))
+    }
+
+    /// Runs `command` on the switch. Every name given on the command line is
+    /// resolved before connecting, so a typo is reported as such even when the
+    /// switch is offline.
+    fn execute(&self, command: DeviceCommand, configuration: Configuration) -> Result<()> {
+        let style = self.color.style();
+        let switch = Switch::from_configuration(&configuration)?;
+
+        match command {
+            DeviceCommand::Switch(switch_opts) => {
+                let routes = switch_opts.routes()?;
+                check_routes(&switch, &routes)?;
 
-                telnet
-                    .write(&buffer.as_bytes())
-                    .expect("Error sending command to HDMI switch");
+                let mut client = SwitchClient::connect(&configuration)?;
+                let applied = client.apply_routes(&routes)?;
+                check_power_on(client.switch(), applied)?;
             }
-            Some(SubCommand::Ls {}) => {
-                println!("Aliases:");
-                switch.list_input_aliases();
-                println!();
-                switch.list_output_aliases();
-                println!();
-                println!();
-                println!("Defaults:");
-                switch.list_input_defaults();
-                println!();
-                switch.list_output_defaults();
+            DeviceCommand::Scene(scene_opts) => {
+                let mut routes = switch.scene(&scene_opts.name)?.to_vec();
+                if scene_opts.power_on {
+                    for route in routes.iter_mut() {
+                        route.power_on = true;
+                    }
+                }
+                check_routes(&switch, &routes)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                let applied = client.apply_routes(&routes)?;
+                check_power_on(client.switch(), applied)?;
             }
-            None => {
-                return Err(anyhow!("No subcommand found. Please use -h for available subcommands"));
+            DeviceCommand::Cec(cec_command) => {
+                let (output, command) = cec_command.command();
+                switch.resolve_cec_output(output, command)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                client.cec(output, command)?;
+            }
+            DeviceCommand::Status {} => {
+                let mut client = SwitchClient::connect(&configuration)?;
+                let routing = client.routing()?;
+                let view = client.switch().routing_view(&routing);
+
+                self.output_format
+                    .print(&view, || print!("{}", view.table().render(&style)))?;
+            }
+            DeviceCommand::Ls {} => {
+                let view = switch.view();
+
+                self.output_format
+                    .print(&view, || print!("{}", view.table().render(&style)))?;
+            }
+            DeviceCommand::Edid(EdidCommand::Get { input }) => {
+                if let Some(input) = &input {
+                    switch.resolve_input(input)?;
+                }
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                let edid = match input {
+                    Some(input) => vec![client.edid(&input)?],
+                    None => client.edids()?,
+                };
+                let view = client.switch().edid_view(&edid);
+
+                self.output_format
+                    .print(&view, || print!("{}", view.table().render(&style)))?;
+            }
+            DeviceCommand::Edid(EdidCommand::Set { input, edid }) => {
+                switch.resolve_input(&input)?;
+                switch.resolve_edid(&edid)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                client.set_edid(&input, &edid)?;
+            }
+            DeviceCommand::Edid(EdidCommand::ListPresets {}) => {
+                let view = PresetsView::new(switch.profile().edid_presets());
+
+                self.output_format
+                    .print(&view, || print!("{}", view.table().render(&style)))?;
+            }
+            DeviceCommand::Audio(AudioCommand::Get { output }) => {
+                if let Some(output) = &output {
+                    switch.resolve_audio_target(output)?;
+                }
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                let audio = match output {
+                    Some(output) => vec![client.audio(&output)?],
+                    None => client.audios()?,
+                };
+                let view = client.switch().audio_view(&audio);
+
+                self.output_format
+                    .print(&view, || print!("{}", view.table().render(&style)))?;
+            }
+            DeviceCommand::Audio(AudioCommand::Set { output, source }) => {
+                switch.resolve_audio_change(&output, &source)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                client.set_audio(&output, &source)?;
+            }
+            DeviceCommand::Audio(AudioCommand::Arc { output, state }) => {
+                let on = matches!(state, Toggle::On);
+                switch.resolve_arc_output(&output)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                client.set_arc(&output, on)?;
+            }
+            DeviceCommand::Audio(AudioCommand::Mute { output }) => {
+                switch.resolve_mute_target(&output)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                client.set_mute(&output, true)?;
+            }
+            DeviceCommand::Audio(AudioCommand::Unmute { output }) => {
+                switch.resolve_mute_target(&output)?;
+
+                let mut client = SwitchClient::connect(&configuration)?;
+                client.set_mute(&output, false)?;
             }
         }
-        return Ok(());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = HdmiSwitchError;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(HdmiSwitchError::Usage(format!("Unknown output format {}", format))),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Prints `view` as a JSON or YAML document, or calls `text` to print the
+    /// human readable version.
+    fn print<T: Serialize>(&self, view: &T, text: impl FnOnce()) -> Result<()> {
+        match self {
+            OutputFormat::Text => text(),
+            OutputFormat::Json => {
+                let json = serde_json::to_string_pretty(view)
+                    .map_err(|err| HdmiSwitchError::Render(err.to_string()))?;
+                println!("{}", json);
+            }
+            OutputFormat::Yaml => {
+                let yaml = serde_yaml::to_string(view)
+                    .map_err(|err| HdmiSwitchError::Render(err.to_string()))?;
+                print!("{}", yaml);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = HdmiSwitchError;
+
+    fn from_str(choice: &str) -> Result<Self> {
+        match choice {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(HdmiSwitchError::Usage(format!("Unknown color choice {}", choice))),
+        }
+    }
+}
+
+impl ColorChoice {
+    fn style(&self) -> Style {
+        let style = Style::detect();
+        match self {
+            ColorChoice::Auto => style,
+            ColorChoice::Always => Style {
+                colour: true,
+                ..style
+            },
+            ColorChoice::Never => Style {
+                colour: false,
+                ..style
+            },
+        }
     }
 }
 
 #[derive(Debug, StructOpt)]
 enum SubCommand {
+    #[structopt(flatten)]
+    Device(DeviceCommand),
+    /// Manage the configuration file
+    Config(ConfigCommand),
+}
+
+/// Commands run against a switch once its configuration is loaded.
+#[derive(Debug, StructOpt)]
+enum DeviceCommand {
     Switch(SwitchOptions),
+    /// Apply all routes of a scene from the configuration
+    Scene(SceneOptions),
     Ls {},
+    /// Show which input is currently routed to each output
+    Status {},
+    /// Show or choose the EDID each input advertises
+    Edid(EdidCommand),
+    /// Control the display on an output over HDMI-CEC
+    Cec(CecOptions),
+    /// Show or choose the audio of the HDMI and analog audio outputs
+    Audio(AudioCommand),
+}
+
+#[derive(Debug, StructOpt)]
+enum EdidCommand {
+    /// Show the EDID of an input, or of every input
+    Get {
+        /// Input alias or API name
+        input: Option<String>,
+    },
+    /// Make an input advertise a preset or the EDID of an output's display
+    Set {
+        /// Input alias or API name
+        input: String,
+        /// Preset from list-presets, or an output alias or API name to copy from
+        edid: String,
+    },
+    /// List the EDID presets built into the switch
+    ListPresets {},
+}
+
+#[derive(Debug, StructOpt)]
+enum AudioCommand {
+    /// Show the audio source, mute and ARC state of an output, or of every output
+    Get {
+        /// Output or audio output alias or API name
+        output: Option<String>,
+    },
+    /// Choose the audio an output plays
+    Set {
+        /// Output or audio output alias or API name
+        output: String,
+        /// follow, an input alias or API name, or arc:<output> for ARC audio
+        source: String,
+    },
+    /// Turn the audio return channel of an output on or off
+    Arc {
+        /// Output alias or API name
+        output: String,
+        #[structopt(possible_values = &["on", "off"])]
+        state: Toggle,
+    },
+    /// Mute an output
+    Mute {
+        /// Output or audio output alias or API name
+        output: String,
+    },
+    /// Unmute an output
+    Unmute {
+        /// Output or audio output alias or API name
+        output: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Toggle {
+    On,
+    Off,
+}
+
+impl FromStr for Toggle {
+    type Err = HdmiSwitchError;
+
+    fn from_str(state: &str) -> Result<Self> {
+        match state {
+            "on" => Ok(Toggle::On),
+            "off" => Ok(Toggle::Off),
+            _ => Err(HdmiSwitchError::Usage(format!("Unknown state {}", state))),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Check the configuration file without connecting to the switch
+    Validate {},
+    /// Create the configuration file by answering a few questions
+    Init {
+        /// Replace the configuration file if it already exists
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Print the configuration merged from every file and variable
+    Show {
+        /// Include defaults and where each value came from
+        #[structopt(long)]
+        resolved: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum CecOptions {
+    /// Turn the display on
+    PowerOn {
+        /// Output alias or API name
+        output: String,
+    },
+    /// Turn the display off
+    PowerOff {
+        /// Output alias or API name, or all
+        output: String,
+    },
+    /// Make the display show the input the switch is connected to
+    InputSource {
+        /// Output alias or API name
+        output: String,
+    },
+    /// Change the volume of the display: up, down or mute
+    Volume {
+        /// Output alias or API name
+        output: String,
+        #[structopt(possible_values = &["up", "down", "mute"])]
+        change: Volume,
+    },
+}
+
+impl CecOptions {
+    /// The output to send the command to and the command.
+    fn command(&self) -> (&str, CecCommand) {
+        match self {
+            CecOptions::PowerOn { output } => (output, CecCommand::PowerOn),
+            CecOptions::PowerOff { output } => (output, CecCommand::PowerOff),
+            CecOptions::InputSource { output } => (output, CecCommand::InputSource),
+            CecOptions::Volume { output, change } => (output, change.command()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Volume {
+    Up,
+    Down,
+    Mute,
+}
+
+impl FromStr for Volume {
+    type Err = HdmiSwitchError;
+
+    fn from_str(change: &str) -> Result<Self> {
+        match change {
+            "up" => Ok(Volume::Up),
+            "down" => Ok(Volume::Down),
+            "mute" => Ok(Volume::Mute),
+            _ => Err(HdmiSwitchError::Usage(format!("Unknown volume change {}", change))),
+        }
+    }
+}
+
+impl Volume {
+    fn command(&self) -> CecCommand {
+        match self {
+            Volume::Up => CecCommand::VolumeUp,
+            Volume::Down => CecCommand::VolumeDown,
+            Volume::Mute => CecCommand::Mute,
+        }
+    }
 }
 
 #[derive(Debug, StructOpt)]
 struct SwitchOptions {
-    #[structopt(short, long)]
-    input: String,
-    #[structopt(short, long)]
-    output: String,
+    /// Routes in the form input:output, e.g. pc:tv
+    routes: Vec<RouteConfiguration>,
+    /// May be repeated, each input is paired with the output in the same position
+    #[structopt(short, long, number_of_values = 1)]
+    input: Vec<String>,
+    #[structopt(short, long, number_of_values = 1)]
+    output: Vec<String>,
+    /// Power on the display on each output over HDMI-CEC after switching
+    #[structopt(long)]
+    power_on: bool,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let opt = Opt::from_args();
+impl SwitchOptions {
+    fn routes(&self) -> Result<Vec<RouteConfiguration>> {
+        if self.input.len() != self.output.len() {
+            return Err(HdmiSwitchError::Usage(format!(
+                "Each --input needs a matching --output, got {} inputs and {} outputs",
+                self.input.len(),
+                self.output.len()
+            )));
+        }
+
+        let mut routes: Vec<RouteConfiguration> = self.routes.clone();
+        for (input, output) in self.input.iter().zip(self.output.iter()) {
+            routes.push(RouteConfiguration {
+                input: input.to_string(),
+                output: output.to_string(),
+                power_on: false,
+            });
+        }
+        if self.power_on {
+            for route in routes.iter_mut() {
+                route.power_on = true;
+            }
+        }
 
-    let configuration_file_path: String = opt
-        .get_file_path()
-        .expect("unable to find configuration file");
+        if routes.is_empty() {
+            return Err(HdmiSwitchError::Usage(
+                "No routes given. Use input:output or --input and --output".to_string(),
+            ));
+        }
 
-    let configuration = configuration::get_configuration(configuration_file_path)?;
+        Ok(routes)
+    }
+}
 
-    opt.execute(configuration)?;
+#[derive(Debug, StructOpt)]
+struct SceneOptions {
+    name: String,
+    /// Power on the display on every output of the scene over HDMI-CEC
+    #[structopt(long)]
+    power_on: bool,
+}
 
+/// Resolves every route so unknown names are reported before connecting.
+fn check_routes(switch: &Switch, routes: &[RouteConfiguration]) -> Result<()> {
+    for route in routes.iter() {
+        switch.resolve_route(&route.input, &route.output)?;
+    }
     Ok(())
 }
+
+/// Reports the displays that didn't power on once every route was applied,
+/// failing with the first of them.
+fn check_power_on(switch: &Switch, applied: AppliedRoutes) -> Result<()> {
+    let mut failures = applied.power_on_failures.into_iter();
+    let Some((_output, first)) = failures.next() else {
+        return Ok(());
+    };
+
+    eprintln!("Every route was applied, but not every display could be powered on");
+    for (output, err) in failures {
+        eprintln!("Warning: {}: {}", switch.display_output(output), err);
+    }
+    Err(first)
+}
+
+fn parse_timeout(timeout: &str) -> Result<f64, String> {
+    let timeout: f64 = timeout.parse().map_err(|err| format!("{}", err))?;
+    configuration::check_timeout(timeout).map_err(|err| err.to_string())
+}
+
+fn run() -> Result<()> {
+    let mut opt = match Opt::clap().setting(AppSettings::ColorNever).get_matches_safe() {
+        Ok(matches) => Opt::from_clap(&matches),
+        Err(err) if err.use_stderr() => {
+            // main adds its own "Error:" in front, clap's message ends with usage
+            let message = err.message.strip_prefix("error: ").unwrap_or(&err.message);
+            return Err(HdmiSwitchError::Usage(message.to_string()));
+        }
+        // --help and --version, a closed pipe such as `| head` is not an error
+        Err(err) => {
+            let _ = writeln!(io::stdout(), "{}", err.message);
+            return Ok(());
+        }
+    };
+
+    let command = match opt.cmd.take() {
+        Some(SubCommand::Config(command)) => return opt.config(command),
+        Some(SubCommand::Device(command)) => command,
+        None => {
+            return Err(HdmiSwitchError::Usage(
+                "No subcommand found. Please use -h for available subcommands".to_string(),
+            ));
+        }
+    };
+
+    let resolved = sources::resolve(opt.layers()?)?;
+    resolved.configuration.check()?;
+    let listing_devices = opt.device.is_none() && !resolved.configuration.devices.is_empty();
+    if let (DeviceCommand::Ls {}, true) = (&command, listing_devices) {
+        return opt.list_devices(&resolved);
+    }
+
+    let configuration = resolved.device(opt.device.as_deref())?;
+    configuration.check()?;
+
+    opt.execute(command, configuration)
+}
+
+fn main() {
+    if let Err(err) = run() {
+        let mut message = format!("Error: {}", err);
+        let mut source = err.source();
+        while let Some(cause) = source {
+            message.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+        eprintln!("{}", message);
+
+        process::exit(err.exit_code());
+    }
+}
@@ -0,0 +1,247 @@
+use crate::error::{HdmiSwitchError, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// An HDMI input on the switch. The API names are `hdmiin1` to `hdmiin4`,
+/// matched without regard to case like every API name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Input {
+    Hdmi1,
+    Hdmi2,
+    Hdmi3,
+    Hdmi4,
+}
+
+/// An HDMI output on the switch. The API names are `hdmiout1` to `hdmiout4`,
+/// plus `all` which addresses every output at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Output {
+    Hdmi1,
+    Hdmi2,
+    Hdmi3,
+    Hdmi4,
+    All,
+}
+
+/// An analog audio output on the switch, carrying audio de-embedded from the
+/// HDMI signals. The API names are `audioout1` to `audioout4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AudioOutput {
+    Analog1,
+    Analog2,
+    Analog3,
+    Analog4,
+}
+
+impl Input {
+    pub const PORTS: [Input; 4] = [Input::Hdmi1, Input::Hdmi2, Input::Hdmi3, Input::Hdmi4];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Input::Hdmi1 => "hdmiin1",
+            Input::Hdmi2 => "hdmiin2",
+            Input::Hdmi3 => "hdmiin3",
+            Input::Hdmi4 => "hdmiin4",
+        }
+    }
+}
+
+impl Output {
+    /// The physical outputs, i.e. every output except [`Output::All`].
+    pub const PORTS: [Output; 4] = [Output::Hdmi1, Output::Hdmi2, Output::Hdmi3, Output::Hdmi4];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Output::Hdmi1 => "hdmiout1",
+            Output::Hdmi2 => "hdmiout2",
+            Output::Hdmi3 => "hdmiout3",
+            Output::Hdmi4 => "hdmiout4",
+            Output::All => "all",
+        }
+    }
+}
+
+impl AudioOutput {
+    pub const PORTS: [AudioOutput; 4] = [
+        AudioOutput::Analog1,
+        AudioOutput::Analog2,
+        AudioOutput::Analog3,
+        AudioOutput::Analog4,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioOutput::Analog1 => "audioout1",
+            AudioOutput::Analog2 => "audioout2",
+            AudioOutput::Analog3 => "audioout3",
+            AudioOutput::Analog4 => "audioout4",
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for AudioOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Input {
+    type Err = HdmiSwitchError;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match Input::PORTS.iter().find(|port| port.as_str().eq_ignore_ascii_case(input)) {
+            Some(port) => Ok(*port),
+            None => Err(HdmiSwitchError::InvalidPort(format!(
+                "{} is not a supported HDMI input",
+                input
+            ))),
+        }
+    }
+}
+
+impl FromStr for Output {
+    type Err = HdmiSwitchError;
+
+    fn from_str(output: &str) -> Result<Self> {
+        if Output::All.as_str().eq_ignore_ascii_case(output) {
+            return Ok(Output::All);
+        }
+
+        match Output::PORTS.iter().find(|port| port.as_str().eq_ignore_ascii_case(output)) {
+            Some(port) => Ok(*port),
+            None => Err(HdmiSwitchError::InvalidPort(format!(
+                "{} is not a supported HDMI output",
+                output
+            ))),
+        }
+    }
+}
+
+impl FromStr for AudioOutput {
+    type Err = HdmiSwitchError;
+
+    fn from_str(output: &str) -> Result<Self> {
+        match AudioOutput::PORTS
+            .iter()
+            .find(|port| port.as_str().eq_ignore_ascii_case(output))
+        {
+            Some(port) => Ok(*port),
+            None => Err(HdmiSwitchError::InvalidPort(format!(
+                "{} is not a supported audio output",
+                output
+            ))),
+        }
+    }
+}
+
+impl Serialize for Input {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for Output {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for AudioOutput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Read through [`FromStr`] so configuration files follow the same case rule
+/// as the command line.
+impl<'de> Deserialize<'de> for Input {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Output {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for AudioOutput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Port numbers start at 1 as they are printed on the switch.
+impl From<Input> for u8 {
+    fn from(input: Input) -> u8 {
+        match input {
+            Input::Hdmi1 => 1,
+            Input::Hdmi2 => 2,
+            Input::Hdmi3 => 3,
+            Input::Hdmi4 => 4,
+        }
+    }
+}
+
+/// Port numbers start at 1 as they are printed on the switch, [`Output::All`]
+/// is 0.
+impl From<Output> for u8 {
+    fn from(output: Output) -> u8 {
+        match output {
+            Output::All => 0,
+            Output::Hdmi1 => 1,
+            Output::Hdmi2 => 2,
+            Output::Hdmi3 => 3,
+            Output::Hdmi4 => 4,
+        }
+    }
+}
+
+impl TryFrom<u8> for Input {
+    type Error = HdmiSwitchError;
+
+    fn try_from(number: u8) -> Result<Self> {
+        match Input::PORTS.iter().find(|port| u8::from(**port) == number) {
+            Some(port) => Ok(*port),
+            None => Err(HdmiSwitchError::InvalidPort(format!(
+                "{} is not a supported HDMI input number",
+                number
+            ))),
+        }
+    }
+}
+
+impl TryFrom<u8> for Output {
+    type Error = HdmiSwitchError;
+
+    fn try_from(number: u8) -> Result<Self> {
+        if number == 0 {
+            return Ok(Output::All);
+        }
+
+        match Output::PORTS.iter().find(|port| u8::from(**port) == number) {
+            Some(port) => Ok(*port),
+            None => Err(HdmiSwitchError::InvalidPort(format!(
+                "{} is not a supported HDMI output number",
+                number
+            ))),
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use crate::configuration::{check_timeout, parse_error, Configuration};
+use crate::error::{HdmiSwitchError, Result};
+use crate::table::{Column, Table};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Configuration file shared by every user of the machine.
+pub const SYSTEM_PATH: &str = "/etc/hdmi-switch/configuration.yaml";
+
+/// Environment variable replacing [`SYSTEM_PATH`], e.g. for packages
+/// installed under another prefix. Unlike the [`ENVIRONMENT`] variables it
+/// sets no value itself, it only moves the system file.
+pub const SYSTEM_PATH_VARIABLE: &str = "HDMI_SWITCH_SYSTEM_CONFIGURATION_PATH";
+
+/// Name of the project-local configuration file, looked up in the current
+/// directory and its parents.
+pub const PROJECT_FILE_NAME: &str = ".hdmi-switch.yaml";
+
+/// Environment variables overriding `server` settings and the setting each
+/// one overrides.
+pub const ENVIRONMENT: [(&str, &str); 5] = [
+    ("HDMI_SWITCH_HOST", "host"),
+    ("HDMI_SWITCH_PORT", "port"),
+    ("HDMI_SWITCH_MODEL", "model"),
+    ("HDMI_SWITCH_CONNECT_TIMEOUT", "connect_timeout"),
+    ("HDMI_SWITCH_READ_TIMEOUT", "read_timeout"),
+];
+
+/// Where a configuration value came from, in order of precedence, lowest
+/// first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    /// The file given with `-c`, which replaces the three above.
+    File(PathBuf),
+    Environment(String),
+    CommandLine(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => f.write_str("default"),
+            Source::System(path)
+            | Source::User(path)
+            | Source::Project(path)
+            | Source::File(path) => write!(f, "{}", path.display()),
+            Source::Environment(variable) => write!(f, "${}", variable),
+            Source::CommandLine(flag) => f.write_str(flag),
+        }
+    }
+}
+
+impl Serialize for Source {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Settings from one source. Files are kept as text and parsed from it on
+/// their own, so errors point at the right line, before being turned into a
+/// [`Value`] when they have to be merged.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub source: Source,
+    content: Content,
+}
+
+#[derive(Debug, Clone)]
+enum Content {
+    Text(String),
+    Value(Value),
+}
+
+impl Layer {
+    /// Reads the file `source` points at. Fails for sources that aren't
+    /// files.
+    pub fn file(source: Source) -> Result<Self> {
+        let path = match &source {
+            Source::System(path) | Source::User(path) | Source::Project(path) | Source::File(path) => {
+                path.to_string_lossy().to_string()
+            }
+            source => {
+                return Err(HdmiSwitchError::ConfigNotFound {
+                    path: source.to_string(),
+                    source: io::Error::new(io::ErrorKind::InvalidInput, "not a file"),
+                });
+            }
+        };
+
+        let text = fs::read_to_string(&path).map_err(|source| HdmiSwitchError::ConfigNotFound {
+            path: path.clone(),
+            source,
+        })?;
+
+        Ok(Layer {
+            source,
+            content: Content::Text(text),
+        })
+    }
+
+    /// The text of a layer read from a file.
+    pub fn text(&self) -> Option<&str> {
+        match &self.content {
+            Content::Text(text) => Some(text),
+            Content::Value(_value) => None,
+        }
+    }
+
+    /// Settings of this layer as a YAML value.
+    pub fn value(&self) -> Result<Value> {
+        match &self.content {
+            Content::Text(text) => {
+                let value: Value = serde_yaml::from_str(text)
+                    .map_err(|err| parse_error(&self.source.to_string(), &err))?;
+                // An empty file is as good as an empty mapping.
+                match value {
+                    Value::Null => Ok(Value::Mapping(Mapping::new())),
+                    value => Ok(value),
+                }
+            }
+            Content::Value(value) => Ok(value.clone()),
+        }
+    }
+
+    /// Layer setting `server.<key>` to the value of each `(key, value)`.
+    pub fn server(source: Source, settings: Vec<(&str, Value)>) -> Self {
+        let mut server = Mapping::new();
+        for (key, value) in settings {
+            server.insert(Value::from(key), value);
+        }
+        let mut root = Mapping::new();
+        root.insert(Value::from("server"), Value::Mapping(server));
+
+        Layer {
+            source,
+            content: Content::Value(Value::Mapping(root)),
+        }
+    }
+
+    /// Built-in values of the optional settings.
+    pub fn defaults() -> Self {
+        Layer::server(
+            Source::Default,
+            vec![
+                ("port", Value::from(23)),
+                ("model", Value::from("4KMX44-H2")),
+                ("connect_timeout", Value::from(5.0)),
+                ("read_timeout", Value::from(5.0)),
+            ],
+        )
+    }
+
+    /// One layer per `HDMI_SWITCH_*` variable found in `variables`, see
+    /// [`ENVIRONMENT`].
+    pub fn environment(
+        variables: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Vec<Self>> {
+        let variables: IndexMap<String, String> = variables.into_iter().collect();
+        let mut layers: Vec<Layer> = Vec::new();
+        for (variable, key) in ENVIRONMENT {
+            let Some(text) = variables.get(variable) else {
+                continue;
+            };
+            let invalid = |message: String| {
+                HdmiSwitchError::InvalidConfiguration(format!("{}: {}", variable, message))
+            };
+            let value = match key {
+                "port" => match text.parse::<u16>() {
+                    Ok(port) if port > 0 => Value::from(port),
+                    _ => return Err(invalid(format!("{} is not a port number", text))),
+                },
+                "connect_timeout" | "read_timeout" => {
+                    let timeout: f64 = text
+                        .parse()
+                        .map_err(|_err| invalid(format!("{} is not a number of seconds", text)))?;
+                    Value::from(check_timeout(timeout).map_err(|err| invalid(err.to_string()))?)
+                }
+                _ => Value::from(text.as_str()),
+            };
+            layers.push(Layer::server(
+                Source::Environment(variable.to_string()),
+                vec![(key, value)],
+            ));
+        }
+
+        Ok(layers)
+    }
+}
+
+/// The configuration files to read, lowest precedence first. With `explicit`
+/// only that file is read, otherwise the system, user and project files that
+/// exist. Fails if there are none.
+pub fn discover(explicit: Option<&str>, current_directory: &Path) -> Result<Vec<Source>> {
+    if let Some(path) = explicit {
+        return Ok(vec![Source::File(PathBuf::from(path))]);
+    }
+
+    let mut candidates: Vec<Source> = vec![Source::System(system_path())];
+    if let Some(path) = user_path() {
+        candidates.push(Source::User(path));
+    }
+    if let Some(path) = project_path(current_directory) {
+        candidates.push(Source::Project(path));
+    }
+
+    let found: Vec<Source> = candidates
+        .iter()
+        .filter(|source| match source {
+            Source::System(path) | Source::User(path) | Source::Project(path) => path.is_file(),
+            _ => false,
+        })
+        .cloned()
+        .collect();
+    if found.is_empty() {
+        let mut searched: Vec<String> = candidates.iter().map(|source| source.to_string()).collect();
+        if !candidates.iter().any(|source| matches!(source, Source::Project(_))) {
+            searched.push(format!("{} in {} or above", PROJECT_FILE_NAME, current_directory.display()));
+        }
+        return Err(HdmiSwitchError::ConfigNotFound {
+            path: searched.join(", "),
+            source: io::Error::new(io::ErrorKind::NotFound, "no configuration file found"),
+        });
+    }
+
+    Ok(found)
+}
+
+/// [`SYSTEM_PATH`], or the file [`SYSTEM_PATH_VARIABLE`] names.
+pub fn system_path() -> PathBuf {
+    match std::env::var_os(SYSTEM_PATH_VARIABLE) {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(SYSTEM_PATH),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/hdmi-switch/configuration.yaml`, falling back to
+/// `$HOME/.config` as the XDG base directory spec says. `None` if neither
+/// variable is set.
+pub fn user_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(directory) if Path::new(&directory).is_absolute() => PathBuf::from(directory),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("hdmi-switch").join("configuration.yaml"))
+}
+
+/// Nearest [`PROJECT_FILE_NAME`] in `directory` or one of its parents.
+pub fn project_path(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|directory| directory.join(PROJECT_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// A configuration merged from several layers, along with the source of each
+/// value.
+#[derive(Debug)]
+pub struct Resolved {
+    pub configuration: Configuration,
+    pub layers: Vec<Layer>,
+}
+
+/// Merges `layers`, later ones winning, into a [`Configuration`]. Mappings
+/// are merged key by key, any other value replaces the one below it.
+pub fn resolve(layers: Vec<Layer>) -> Result<Resolved> {
+    let configuration = match layers.as_slice() {
+        [Layer {
+            source,
+            content: Content::Text(text),
+        }] => serde_yaml::from_str(text).map_err(|err| parse_error(&source.to_string(), &err))?,
+        layers => {
+            // Each file is read on its own first, errors in the merged value
+            // have no line to point at.
+            for layer in layers.iter() {
+                if let Some(text) = layer.text() {
+                    serde_yaml::from_str::<Configuration>(text)
+                        .map_err(|err| parse_error(&layer.source.to_string(), &err))?;
+                }
+            }
+
+            let mut merged = Value::Mapping(Mapping::new());
+            for layer in layers.iter() {
+                let value = layer.value()?;
+                forget_aliases(&mut merged, &value);
+                merge(&mut merged, &value);
+            }
+            let sources: Vec<String> = layers.iter().map(|layer| layer.source.to_string()).collect();
+            serde_yaml::from_value(merged)
+                .map_err(|err| parse_error(&sources.join(" + "), &err))?
+        }
+    };
+
+    Ok(Resolved {
+        configuration,
+        layers,
+    })
+}
+
+fn merge(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay.iter() {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// Drops the aliases `overlay` defines, in a flat map or under a port, from
+/// the same section of `base`. A later layer may then move an alias to
+/// another port or map, as a device section can.
+fn forget_aliases(base: &mut Value, overlay: &Value) {
+    for section in ["input", "output", "audio"] {
+        if let (Some(base), Some(overlay)) = (base.get_mut(section), overlay.get(section)) {
+            forget_section_aliases(base, overlay);
+        }
+    }
+
+    if let (Some(Value::Mapping(base)), Some(Value::Mapping(overlay))) =
+        (base.get_mut("devices"), overlay.get("devices"))
+    {
+        for (name, device) in overlay.iter() {
+            if let Some(base) = base.get_mut(name) {
+                forget_aliases(base, device);
+            }
+        }
+    }
+}
+
+fn forget_section_aliases(base: &mut Value, overlay: &Value) {
+    let mut defined: Vec<&Value> = Vec::new();
+    if let Some(Value::Mapping(aliases)) = overlay.get("aliases") {
+        defined.extend(aliases.keys());
+    }
+    if let Some(Value::Mapping(ports)) = overlay.get("ports") {
+        for port in ports.values() {
+            if let Some(Value::Sequence(aliases)) = port.get("aliases") {
+                defined.extend(aliases.iter());
+            }
+        }
+    }
+
+    if let Some(Value::Mapping(aliases)) = base.get_mut("aliases") {
+        aliases.retain(|alias, _port| !defined.contains(&alias));
+    }
+    if let Some(Value::Mapping(ports)) = base.get_mut("ports") {
+        for port in ports.values_mut() {
+            if let Some(Value::Sequence(aliases)) = port.get_mut("aliases") {
+                aliases.retain(|alias| !defined.contains(&alias));
+            }
+        }
+    }
+}
+
+/// Adds the keys of `defaults` missing from `base`, after the keys `base`
+/// already has.
+fn merge_defaults(base: &mut Value, defaults: &Value) {
+    if let (Value::Mapping(base), Value::Mapping(defaults)) = (base, defaults) {
+        for (key, value) in defaults.iter() {
+            match base.get_mut(key) {
+                Some(existing) => merge_defaults(existing, value),
+                None => {
+                    base.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Serializable result of `config show`.
+#[derive(Serialize, Debug)]
+pub struct ResolvedView {
+    /// Sources in order of precedence, lowest first.
+    pub sources: Vec<Source>,
+    pub values: Vec<ValueView>,
+}
+
+/// A single setting, e.g. `server.port`, and the source it came from.
+#[derive(Serialize, Debug)]
+pub struct ValueView {
+    pub path: String,
+    pub value: String,
+    pub source: Source,
+}
+
+impl Resolved {
+    /// The switch called `name`, or the default one, see
+    /// [`Configuration::device`]. Variables and flags are applied to the
+    /// device as well, as its own settings would hide them otherwise.
+    pub fn device(&self, name: Option<&str>) -> Result<Configuration> {
+        let mut configuration = self.configuration.device(name)?;
+        if self.configuration.devices.is_empty() {
+            return Ok(configuration);
+        }
+
+        let mut server = serde_yaml::to_value(&configuration.server)
+            .map_err(|err| HdmiSwitchError::Render(err.to_string()))?;
+        let mut sources: Vec<String> = Vec::new();
+        for layer in self.layers.iter() {
+            if let Source::Environment(_) | Source::CommandLine(_) = layer.source {
+                if let Some(overrides) = layer.value()?.get("server") {
+                    merge(&mut server, overrides);
+                    sources.push(layer.source.to_string());
+                }
+            }
+        }
+        configuration.server = serde_yaml::from_value(server)
+            .map_err(|err| parse_error(&sources.join(" + "), &err))?;
+
+        Ok(configuration)
+    }
+
+    /// Every setting of the configuration with the source that set it.
+    /// Settings left out of every file are listed with their default.
+    pub fn view(&self) -> Result<ResolvedView> {
+        let defaults = Layer::defaults().value()?;
+        let mut sources: IndexMap<String, Source> = leaves(&defaults)
+            .into_iter()
+            .map(|(path, _value)| (path, Source::Default))
+            .collect();
+
+        let mut merged = Value::Mapping(Mapping::new());
+        for layer in self.layers.iter() {
+            let value = layer.value()?;
+            merge(&mut merged, &value);
+            for (path, _value) in leaves(&value) {
+                sources.insert(path, layer.source.clone());
+            }
+        }
+        merge_defaults(&mut merged, &defaults);
+
+        let mut layers = vec![Source::Default];
+        layers.extend(self.layers.iter().map(|layer| layer.source.clone()));
+
+        Ok(ResolvedView {
+            sources: layers,
+            values: leaves(&merged)
+                .into_iter()
+                .map(|(path, value)| ValueView {
+                    source: sources.get(&path).cloned().unwrap_or(Source::Default),
+                    path,
+                    value,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Flattens `value` into `(path, value)` pairs, e.g. `("server.port", "23")`
+/// or `("scenes.work[0].input", "pc")`.
+fn leaves(value: &Value) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = Vec::new();
+    collect_leaves("", value, &mut found);
+    found
+}
+
+fn collect_leaves(path: &str, value: &Value, found: &mut Vec<(String, String)>) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, value) in mapping.iter() {
+                let key = scalar(key);
+                let path = match path {
+                    "" => key,
+                    path => format!("{}.{}", path, key),
+                };
+                collect_leaves(&path, value, found);
+            }
+        }
+        Value::Sequence(sequence) if !sequence.is_empty() => {
+            for (position, value) in sequence.iter().enumerate() {
+                collect_leaves(&format!("{}[{}]", path, position), value, found);
+            }
+        }
+        value => found.push((path.to_string(), scalar(value))),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.to_string(),
+        Value::Mapping(_) => "{}".to_string(),
+        Value::Sequence(_) => "[]".to_string(),
+        value => serde_yaml::to_string(value)
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+impl ResolvedView {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new(), Column::new()]);
+
+        // Sources are headings so long paths don't widen the value columns.
+        table.heading(0, "Sources, lowest precedence first:");
+        for source in self.sources.iter() {
+            table.heading(2, &source.to_string());
+        }
+        table.blank();
+        table.heading(0, "Values:");
+        for value in self.values.iter() {
+            table.row(
+                2,
+                vec![format!("{}:", value.path), value.value.clone(), value.source.to_string()],
+            );
+        }
+
+        table
+    }
+}
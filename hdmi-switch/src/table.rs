@@ -0,0 +1,270 @@
+use std::io::{self, IsTerminal};
+
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+/// Printed between cells.
+const SEPARATOR: &str = " ";
+
+/// How a column of a [`Table`] is laid out.
+#[derive(Debug, Clone)]
+pub struct Column {
+    /// Highlight the cells of this column when colour is enabled.
+    pub highlight: bool,
+}
+
+impl Column {
+    pub fn new() -> Self {
+        Column { highlight: false }
+    }
+
+    pub fn highlight(mut self) -> Self {
+        self.highlight = true;
+        self
+    }
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Row {
+    Heading { indent: usize, text: String },
+    Cells { indent: usize, cells: Vec<String> },
+    Blank,
+}
+
+/// Terminal capabilities a [`Table`] is rendered for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Style {
+    pub colour: bool,
+    /// Lines longer than this are cut short, `None` never cuts.
+    pub width: Option<usize>,
+}
+
+impl Style {
+    /// Style for stdout: colour and the terminal width when stdout is a
+    /// terminal and `NO_COLOR` is unset, plain text otherwise.
+    pub fn detect() -> Self {
+        if !io::stdout().is_terminal() {
+            return Style::default();
+        }
+
+        Style {
+            colour: std::env::var_os("NO_COLOR").is_none(),
+            width: terminal_size::terminal_size().map(|(width, _height)| width.0 as usize),
+        }
+    }
+}
+
+/// Rows of cells aligned into columns, with optional headings between them.
+/// Cells are aligned across the whole table, headings included, so a table
+/// made of several sections lines up as one.
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Table {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn heading(&mut self, indent: usize, text: &str) {
+        self.rows.push(Row::Heading {
+            indent,
+            text: text.to_string(),
+        });
+    }
+
+    /// Adds a row of cells. Missing trailing cells are left empty.
+    pub fn row(&mut self, indent: usize, cells: Vec<String>) {
+        self.rows.push(Row::Cells { indent, cells });
+    }
+
+    pub fn blank(&mut self) {
+        self.rows.push(Row::Blank);
+    }
+
+    pub fn render(&self, style: &Style) -> String {
+        let widths = self.widths();
+        let mut rendered = String::new();
+
+        for row in self.rows.iter() {
+            let line = match row {
+                Row::Heading { indent, text } => {
+                    let text = truncate(&format!("{}{}", " ".repeat(*indent), text), style.width);
+                    paint(&text, BOLD, style)
+                }
+                Row::Cells { indent, cells } => self.line(*indent, cells, &widths, style),
+                Row::Blank => String::new(),
+            };
+            rendered.push_str(&line);
+            rendered.push('\n');
+        }
+
+        rendered
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = vec![0; self.columns.len()];
+
+        for row in self.rows.iter() {
+            if let Row::Cells { cells, .. } = row {
+                for (width, cell) in widths.iter_mut().zip(cells.iter()) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+        }
+
+        widths
+    }
+
+    fn line(&self, indent: usize, cells: &[String], widths: &[usize], style: &Style) -> String {
+        // Trailing empty cells are left out so they don't leave padding behind.
+        let used = cells
+            .iter()
+            .rposition(|cell| !cell.is_empty())
+            .map_or(0, |position| position + 1)
+            .min(self.columns.len());
+
+        let mut plain: Vec<String> = Vec::new();
+        for (position, width) in widths.iter().take(used).enumerate() {
+            let cell = cells[position].as_str();
+            let padding = " ".repeat(width - cell.chars().count());
+            // Don't leave trailing spaces after the last column.
+            match position + 1 == used {
+                true => plain.push(cell.to_string()),
+                false => plain.push(format!("{}{}", cell, padding)),
+            }
+        }
+
+        // Cut the line to the terminal width before colouring it so escape
+        // codes don't count towards the width.
+        let mut remaining = style.width.map(|width| width.saturating_sub(indent));
+        let mut line = " ".repeat(indent);
+        for (position, (column, cell)) in self.columns.iter().zip(plain.iter()).enumerate() {
+            let mut text = String::new();
+            if position > 0 {
+                text.push_str(SEPARATOR);
+            }
+            text.push_str(cell);
+
+            let text = truncate(&text, remaining);
+            if let Some(remaining) = remaining.as_mut() {
+                *remaining = remaining.saturating_sub(text.chars().count());
+            }
+
+            if column.highlight {
+                line.push_str(&paint(&text, CYAN, style));
+            } else {
+                line.push_str(&text);
+            }
+        }
+
+        line
+    }
+}
+
+/// Cuts `text` to `width` characters, marking the cut with an ellipsis.
+fn truncate(text: &str, width: Option<usize>) -> String {
+    match width {
+        Some(width) if text.chars().count() > width => {
+            if width == 0 {
+                return String::new();
+            }
+            let mut truncated: String = text.chars().take(width - 1).collect();
+            truncated.push('…');
+            truncated
+        }
+        _ => text.to_string(),
+    }
+}
+
+fn paint(text: &str, colour: &str, style: &Style) -> String {
+    if !style.colour || text.is_empty() {
+        return text.to_string();
+    }
+
+    format!("{}{}{}", colour, text, RESET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(width: Option<usize>) -> Style {
+        Style {
+            colour: false,
+            width,
+        }
+    }
+
+    fn ports() -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new()]);
+        table.heading(0, "Aliases:");
+        table.row(2, vec!["ps:".to_string(), "hdmiin2".to_string()]);
+        table.row(2, vec!["monitor:".to_string(), "hdmiout1".to_string()]);
+        table.row(2, vec!["tv:".to_string(), String::new()]);
+        table.blank();
+        table
+    }
+
+    #[test]
+    fn cells_are_aligned_across_rows() {
+        assert_eq!(
+            ports().render(&plain(None)),
+            "Aliases:\n  ps:      hdmiin2\n  monitor: hdmiout1\n  tv:\n\n"
+        );
+    }
+
+    #[test]
+    fn lines_are_cut_to_the_width() {
+        assert_eq!(
+            ports().render(&plain(Some(13))),
+            "Aliases:\n  ps:      h…\n  monitor: h…\n  tv:\n\n"
+        );
+        assert_eq!(ports().render(&plain(Some(5))), "Alia…\n  ps…\n  mo…\n  tv:\n\n");
+    }
+
+    #[test]
+    fn truncate_marks_the_cut() {
+        assert_eq!(truncate("hdmiin2", None), "hdmiin2");
+        assert_eq!(truncate("hdmiin2", Some(7)), "hdmiin2");
+        assert_eq!(truncate("hdmiin2", Some(4)), "hdm…");
+        assert_eq!(truncate("hdmiin2", Some(0)), "");
+    }
+
+    #[test]
+    fn colour_wraps_headings_and_highlighted_cells() {
+        let style = Style {
+            colour: true,
+            width: None,
+        };
+
+        assert_eq!(
+            ports().render(&style),
+            "\x1b[1mAliases:\x1b[0m\n\
+             \x20 \x1b[36mps:     \x1b[0m hdmiin2\n\
+             \x20 \x1b[36mmonitor:\x1b[0m hdmiout1\n\
+             \x20 \x1b[36mtv:\x1b[0m\n\n"
+        );
+    }
+
+    #[test]
+    fn colour_does_not_count_towards_the_width() {
+        let style = Style {
+            colour: true,
+            width: Some(13),
+        };
+
+        assert!(ports().render(&style).contains("\x1b[36mps:     \x1b[0m h…\n"));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::configuration::{Configuration, Parity, TransportConfiguration};
+use crate::error::{HdmiSwitchError, Result};
+use serial2::{CharSize, SerialPort, StopBits};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use telnet::{Event, Telnet};
+
+/// Baud rate of the RS-232 control port as the switch ships.
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// A connection to the switch that commands are written to and responses are
+/// read from. [`crate::SwitchClient`] handles the commands themselves.
+pub trait Transport {
+    fn write(&mut self, data: &[u8]) -> Result<()>;
+
+    /// Reads what the switch sent next, waiting at most `timeout`. `None` if
+    /// nothing arrived in time. The data may be empty when the transport only
+    /// handled something of its own, such as a telnet negotiation.
+    fn read(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>>;
+
+    /// Whether the switch sends a welcome message on connect, which is read
+    /// and dropped before the first command.
+    fn has_welcome_message(&self) -> bool {
+        false
+    }
+}
+
+/// Opens the transport `configuration` selects.
+pub fn open(configuration: &Configuration) -> Result<Box<dyn Transport>> {
+    match configuration.get_transport() {
+        TransportConfiguration::Telnet => Ok(Box::new(TelnetTransport::connect(
+            &configuration.server.host,
+            configuration.get_port(),
+            configuration.get_connect_timeout(),
+        )?)),
+        TransportConfiguration::RawTcp { welcome_message } => {
+            Ok(Box::new(RawTcpTransport::connect(
+                &configuration.server.host,
+                configuration.get_port(),
+                configuration.get_connect_timeout(),
+                welcome_message,
+            )?))
+        }
+        TransportConfiguration::Serial {
+            path,
+            baud_rate,
+            parity,
+        } => Ok(Box::new(SerialTransport::open(
+            &path,
+            baud_rate.unwrap_or(DEFAULT_BAUD_RATE),
+            parity,
+        )?)),
+    }
+}
+
+/// Telnet over TCP, the network control port.
+pub struct TelnetTransport {
+    telnet: Telnet,
+}
+
+impl TelnetTransport {
+    pub fn connect(host: &str, port: u16, timeout: Duration) -> Result<Self> {
+        let stream = connect_tcp(host, port, timeout)?;
+
+        Ok(TelnetTransport {
+            telnet: Telnet::from_stream(Box::new(stream), 256),
+        })
+    }
+}
+
+impl Transport for TelnetTransport {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.telnet
+            .write(data)
+            .map(|_written| ())
+            .map_err(|err| HdmiSwitchError::ConnectionLost(err.to_string()))
+    }
+
+    fn read(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        let event = self
+            .telnet
+            .read_timeout(timeout)
+            .map_err(|err| HdmiSwitchError::ConnectionLost(err.to_string()))?;
+
+        match event {
+            Event::Data(data) => Ok(Some(data.to_vec())),
+            Event::TimedOut => Ok(None),
+            // The telnet crate reports a closed connection as an empty read
+            // that leaves nothing in its event queue.
+            Event::Error(err) => Err(HdmiSwitchError::ConnectionLost(err.to_string())),
+            _event => Ok(Some(Vec::new())),
+        }
+    }
+
+    fn has_welcome_message(&self) -> bool {
+        true
+    }
+}
+
+/// A plain TCP socket, for firmware and IP-to-serial gateways that don't
+/// speak telnet. Commands are sent as they are and responses are read up to
+/// the end of each line by [`crate::SwitchClient`].
+pub struct RawTcpTransport {
+    stream: TcpStream,
+    welcome_message: bool,
+}
+
+impl RawTcpTransport {
+    pub fn connect(
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        welcome_message: bool,
+    ) -> Result<Self> {
+        Ok(RawTcpTransport {
+            stream: connect_tcp(host, port, timeout)?,
+            welcome_message,
+        })
+    }
+}
+
+impl Transport for RawTcpTransport {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.stream
+            .write_all(data)
+            .map_err(|err| HdmiSwitchError::ConnectionLost(err.to_string()))
+    }
+
+    fn read(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        self.stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|err| HdmiSwitchError::ConnectionLost(err.to_string()))?;
+
+        let mut buffer = [0u8; 256];
+        match self.stream.read(&mut buffer) {
+            Ok(0) => Err(HdmiSwitchError::ConnectionLost(
+                "connection closed by the HDMI switch".to_string(),
+            )),
+            Ok(size) => Ok(Some(buffer[..size].to_vec())),
+            Err(err) if is_timeout(&err) => Ok(None),
+            Err(err) => Err(HdmiSwitchError::ConnectionLost(err.to_string())),
+        }
+    }
+
+    fn has_welcome_message(&self) -> bool {
+        self.welcome_message
+    }
+}
+
+/// Connects to `host`, trying every address it resolves to until one accepts
+/// within `timeout`.
+fn connect_tcp(host: &str, port: u16, timeout: Duration) -> Result<TcpStream> {
+    let address = format!("{}:{}", host, port);
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|source| HdmiSwitchError::ConnectionFailed {
+            address: address.clone(),
+            source,
+        })?;
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "host has no addresses");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = err,
+        }
+    }
+
+    match is_timeout(&last_error) {
+        true => Err(HdmiSwitchError::Timeout {
+            action: format!("connecting to the HDMI switch at {}", address),
+            timeout,
+        }),
+        false => Err(HdmiSwitchError::ConnectionFailed {
+            address,
+            source: last_error,
+        }),
+    }
+}
+
+/// Sockets with a timeout report it as either kind depending on the platform.
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+}
+
+/// The RS-232 control port, 8 data bits and 1 stop bit.
+pub struct SerialTransport {
+    port: SerialPort,
+}
+
+impl SerialTransport {
+    pub fn open(path: &str, baud_rate: u32, parity: Parity) -> Result<Self> {
+        let port = SerialPort::open(path, |mut settings: serial2::Settings| {
+            settings.set_raw();
+            settings.set_baud_rate(baud_rate)?;
+            settings.set_char_size(CharSize::Bits8);
+            settings.set_stop_bits(StopBits::One);
+            settings.set_parity(match parity {
+                Parity::None => serial2::Parity::None,
+                Parity::Odd => serial2::Parity::Odd,
+                Parity::Even => serial2::Parity::Even,
+            });
+            Ok(settings)
+        })
+        .map_err(|source| HdmiSwitchError::ConnectionFailed {
+            address: path.to_string(),
+            source,
+        })?;
+
+        Ok(SerialTransport { port })
+    }
+}
+
+impl Transport for SerialTransport {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.port
+            .write_all(data)
+            .map_err(|err| HdmiSwitchError::ConnectionLost(err.to_string()))
+    }
+
+    fn read(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        self.port
+            .set_read_timeout(timeout)
+            .map_err(|err| HdmiSwitchError::ConnectionLost(err.to_string()))?;
+
+        let mut buffer = [0u8; 256];
+        match self.port.read(&mut buffer) {
+            Ok(0) => Err(HdmiSwitchError::ConnectionLost(
+                "serial port was closed".to_string(),
+            )),
+            Ok(size) => Ok(Some(buffer[..size].to_vec())),
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(err) => Err(HdmiSwitchError::ConnectionLost(err.to_string())),
+        }
+    }
+}
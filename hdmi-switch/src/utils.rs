@@ -1,229 +1,889 @@
+use crate::audio::{AudioSource, AudioStatus, AudioTarget, AudioView, OutputAudioView};
+use crate::cec::CecCommand;
+use crate::client::Route;
+use crate::configuration::{check_keyword, Configuration, PortConfiguration, RouteConfiguration};
+use crate::device::{Av4kmx44H2, DeviceProfile};
+use crate::edid::{Edid, EdidPreset, EdidView, InputEdidView};
+use crate::error::{HdmiSwitchError, PortKind, Result};
+use crate::port::{AudioOutput, Input, Output};
+use crate::table::{Column, Table};
 use indexmap::IndexMap;
-use anyhow::{Result, anyhow};
+use serde::Serialize;
 
-type HdmiIn<'a> = &'a str;
+/// Serializable snapshot of the names `Switch` resolves, as printed by `ls`.
+#[derive(Serialize, Debug)]
+pub struct SwitchView {
+    pub aliases: PortsView,
+    pub defaults: PortsView,
+    pub ports: PortDetailsView,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PortsView {
+    pub input: IndexMap<String, Input>,
+    pub output: IndexMap<String, Output>,
+    pub audio: IndexMap<String, AudioOutput>,
+}
+
+/// Labels and aliases of each port, the reverse of [`PortsView`].
+#[derive(Serialize, Debug)]
+pub struct PortDetailsView {
+    pub input: IndexMap<Input, PortDetails>,
+    pub output: IndexMap<Output, PortDetails>,
+    pub audio: IndexMap<AudioOutput, PortDetails>,
+}
+
+/// Everything the configuration says about a port: its display name, every
+/// alias in load order, a description and whether routing it is refused.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PortDetails {
+    pub name: Option<String>,
+    pub aliases: Vec<String>,
+    pub description: Option<String>,
+    pub disabled: bool,
+}
+
+/// Details of a port the configuration says nothing about.
+static NO_DETAILS: PortDetails = PortDetails {
+    name: None,
+    aliases: Vec::new(),
+    description: None,
+    disabled: false,
+};
+
+impl PortDetails {
+    /// Formats the port called `api_name` for people to read.
+    pub fn display(&self, api_name: &str) -> String {
+        display_port(api_name, self.name.as_deref(), &self.aliases)
+    }
+
+    /// Whether the configuration says anything about the port.
+    fn is_configured(&self) -> bool {
+        self.name.is_some()
+            || !self.aliases.is_empty()
+            || self.description.is_some()
+            || self.disabled
+    }
+
+    /// The description, marked when the port is disabled.
+    fn notes(&self) -> String {
+        match (&self.description, self.disabled) {
+            (Some(description), true) => format!("{} (disabled)", description),
+            (Some(description), false) => description.to_string(),
+            (None, true) => "(disabled)".to_string(),
+            (None, false) => String::new(),
+        }
+    }
+}
+
+/// Serializable routing table, as printed by `status`.
+#[derive(Serialize, Debug)]
+pub struct RoutingView {
+    pub routing: Vec<RouteView>,
+}
+
+/// A route along with the configured name and aliases of each side.
+#[derive(Serialize, Debug)]
+pub struct RouteView {
+    pub output: Output,
+    pub output_name: Option<String>,
+    pub output_aliases: Vec<String>,
+    pub input: Input,
+    pub input_name: Option<String>,
+    pub input_aliases: Vec<String>,
+}
+
+/// Formats a port for people to read, e.g. `hdmiin2 (ps, playstation)` or
+/// `hdmiin2 PlayStation (ps)` when it has a display name.
+pub fn display_port(api_name: &str, name: Option<&str>, aliases: &[String]) -> String {
+    let mut display = api_name.to_string();
+    if let Some(name) = name {
+        display.push(' ');
+        display.push_str(name);
+    }
+    if !aliases.is_empty() {
+        display.push_str(&format!(" ({})", aliases.join(", ")));
+    }
+
+    display
+}
+
+impl SwitchView {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new(), Column::new()]);
+        self.rows(&mut table, 0);
+
+        table
+    }
+
+    fn rows(&self, table: &mut Table, indent: usize) {
+        table.heading(indent, "Aliases:");
+        // Audio outputs are only listed once configured, most setups only
+        // route video.
+        let audio = self.ports.audio.values().any(PortDetails::is_configured);
+        let headings = ["Input aliases:", "Output aliases:", "Audio aliases:"];
+        self.aliases.rows(table, indent + 2, headings, audio, &self.ports);
+        table.blank();
+        table.blank();
+        table.heading(indent, "Defaults:");
+        let headings = ["Input Defaults:", "Output Defaults:", "Audio Defaults:"];
+        self.defaults.rows(table, indent + 2, headings, audio, &self.ports);
+    }
+}
+
+impl PortsView {
+    /// Adds a row per name under the input, output and, with `audio`, audio
+    /// `headings`. Ports found in `ports` are shown with their labels and
+    /// description.
+    fn rows(
+        &self,
+        table: &mut Table,
+        indent: usize,
+        headings: [&str; 3],
+        audio: bool,
+        ports: &PortDetailsView,
+    ) {
+        let [input_heading, output_heading, audio_heading] = headings;
+        table.heading(indent, input_heading);
+        for (name, input) in self.input.iter() {
+            table.row(indent + 2, port_row(name, input.as_str(), ports.input.get(input)));
+        }
+        table.blank();
+        table.heading(indent, output_heading);
+        for (name, output) in self.output.iter() {
+            table.row(indent + 2, port_row(name, output.as_str(), ports.output.get(output)));
+        }
+        if !audio || self.audio.is_empty() {
+            return;
+        }
+        table.blank();
+        table.heading(indent, audio_heading);
+        for (name, audio) in self.audio.iter() {
+            table.row(indent + 2, port_row(name, audio.as_str(), ports.audio.get(audio)));
+        }
+    }
+}
+
+/// Serializable listing of every configured switch, as printed by `ls` when
+/// no `--device` is given.
+#[derive(Serialize, Debug)]
+pub struct DevicesView {
+    pub devices: IndexMap<String, DeviceView>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeviceView {
+    /// Where the switch is reached, see [`Configuration::get_address`].
+    pub address: String,
+    /// Whether this device is used without `--device`.
+    pub default: bool,
+    #[serde(flatten)]
+    pub switch: SwitchView,
+}
+
+impl DevicesView {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new(), Column::new()]);
+
+        for (position, (name, device)) in self.devices.iter().enumerate() {
+            if position > 0 {
+                table.blank();
+                table.blank();
+            }
+            let default = if device.default { ", default" } else { "" };
+            table.heading(0, &format!("{} ({}{}):", name, device.address, default));
+            device.switch.rows(&mut table, 2);
+        }
+
+        table
+    }
+}
+
+fn port_row(name: &str, api_name: &str, details: Option<&PortDetails>) -> Vec<String> {
+    match details {
+        Some(details) => vec![format!("{}:", name), details.display(api_name), details.notes()],
+        None => vec![format!("{}:", name), api_name.to_string()],
+    }
+}
 
-pub const HDMIIN1: HdmiIn = "hdmiin1";
-pub const HDMIIN2: HdmiIn = "hdmiin2";
-pub const HDMIIN3: HdmiIn = "hdmiin3";
-pub const HDMIIN4: HdmiIn = "hdmiin4";
+impl RoutingView {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new()]);
 
-type HdmiOut<'a> = &'a str;
+        table.heading(0, "Routing:");
+        for route in self.routing.iter() {
+            table.row(
+                2,
+                vec![
+                    format!(
+                        "{}:",
+                        display_port(
+                            route.output.as_str(),
+                            route.output_name.as_deref(),
+                            &route.output_aliases
+                        )
+                    ),
+                    display_port(
+                        route.input.as_str(),
+                        route.input_name.as_deref(),
+                        &route.input_aliases,
+                    ),
+                ],
+            );
+        }
 
-pub const HDMIOUT1: HdmiOut = "hdmiout1";
-pub const HDMIOUT2: HdmiOut = "hdmiout2";
-pub const HDMIOUT3: HdmiOut = "hdmiout3";
-pub const HDMIOUT4: HdmiOut = "hdmiout4";
-pub const HDMIOUTALL: HdmiOut = "all";
+        table
+    }
+}
 
 pub struct Switch {
-    input_defaults: IndexMap<String, String>,
-    input_aliases: IndexMap<String, String>,
+    profile: Box<dyn DeviceProfile>,
+
+    input_defaults: IndexMap<String, Input>,
+    input_aliases: IndexMap<String, Input>,
+
+    output_defaults: IndexMap<String, Output>,
+    output_aliases: IndexMap<String, Output>,
 
-    output_defaults: IndexMap<String, String>,
-    output_aliases: IndexMap<String, String>,
+    // Labels of each port, including the reverse of the alias maps.
+    input_ports: IndexMap<Input, PortDetails>,
+    output_ports: IndexMap<Output, PortDetails>,
 
-    longest_str: usize
+    audio_defaults: IndexMap<String, AudioOutput>,
+    audio_aliases: IndexMap<String, AudioOutput>,
+    audio_ports: IndexMap<AudioOutput, PortDetails>,
+
+    scenes: IndexMap<String, Vec<RouteConfiguration>>,
 }
 
 impl Switch {
     pub fn new() -> Self {
-        let switch = Switch {
-            input_defaults: IndexMap::from([
-                (HDMIIN1.to_string(), HDMIIN1.to_string()),
-                (HDMIIN2.to_string(), HDMIIN2.to_string()),
-                (HDMIIN3.to_string(), HDMIIN3.to_string()),
-                (HDMIIN4.to_string(), HDMIIN4.to_string()),
-            ]),
+        Self::with_profile(Box::new(Av4kmx44H2))
+    }
+
+    /// Builds a `Switch` for the ports and command syntax of `profile`.
+    pub fn with_profile(profile: Box<dyn DeviceProfile>) -> Self {
+        let mut outputs: Vec<Output> = profile.outputs().to_vec();
+        if profile.supports_all_outputs() {
+            outputs.push(Output::All);
+        }
+
+        Switch {
+            input_defaults: profile
+                .inputs()
+                .iter()
+                .map(|input| (profile.input_name(*input), *input))
+                .collect(),
             input_aliases: IndexMap::new(),
-            output_defaults: IndexMap::from([
-                (HDMIOUT1.to_string(), HDMIOUT1.to_string()),
-                (HDMIOUT2.to_string(), HDMIOUT2.to_string()),
-                (HDMIOUT3.to_string(), HDMIOUT3.to_string()),
-                (HDMIOUT4.to_string(), HDMIOUT4.to_string()),
-                (HDMIOUTALL.to_string(), HDMIOUTALL.to_string()),
-            ]),
+            output_defaults: outputs
+                .iter()
+                .map(|output| (profile.output_name(*output), *output))
+                .collect(),
             output_aliases: IndexMap::new(),
-            longest_str: 0,
-        };
-        return switch;
+            input_ports: profile
+                .inputs()
+                .iter()
+                .map(|input| (*input, PortDetails::default()))
+                .collect(),
+            output_ports: outputs
+                .iter()
+                .map(|output| (*output, PortDetails::default()))
+                .collect(),
+            audio_defaults: profile
+                .audio_outputs()
+                .iter()
+                .map(|output| (output.to_string(), *output))
+                .collect(),
+            audio_aliases: IndexMap::new(),
+            audio_ports: profile
+                .audio_outputs()
+                .iter()
+                .map(|output| (*output, PortDetails::default()))
+                .collect(),
+            scenes: IndexMap::new(),
+            profile,
+        }
     }
 
-    pub fn longest_input_output_key(&mut self) -> usize {
-        for (key, _value) in self.input_defaults.iter() {
-            if key.len() > self.longest_str {
-                self.longest_str = key.len()
-            }
+    /// Builds a `Switch` for the configured model with the input and output
+    /// ports and aliases from `configuration`. Aliases keep the order of the
+    /// file, the flat `aliases` maps first.
+    pub fn from_configuration(configuration: &Configuration) -> Result<Self> {
+        let mut switch = Switch::with_profile(configuration.get_model().profile());
+        for (alias, input) in configuration.input.aliases.iter() {
+            switch.load_input_alias(alias, *input)?;
+        }
+        for (input, port) in configuration.input.ports.iter() {
+            switch.load_input_port(*input, port)?;
+        }
+        for (alias, output) in configuration.output.aliases.iter() {
+            switch.load_output_alias(alias, *output)?;
+        }
+        for (output, port) in configuration.output.ports.iter() {
+            switch.load_output_port(*output, port)?;
+        }
+        for (alias, output) in configuration.audio.aliases.iter() {
+            switch.load_audio_alias(alias, *output)?;
+        }
+        for (output, port) in configuration.audio.ports.iter() {
+            switch.load_audio_port(*output, port)?;
+        }
+        for (name, routes) in configuration.scenes.iter() {
+            switch.load_scene(name, routes)?;
         }
 
-        for (key, _value) in self.input_aliases.iter() {
-            if key.len() > self.longest_str {
-                self.longest_str = key.len()
-            }
+        Ok(switch)
+    }
+
+    pub fn profile(&self) -> &dyn DeviceProfile {
+        self.profile.as_ref()
+    }
+
+    pub fn view(&self) -> SwitchView {
+        SwitchView {
+            aliases: PortsView {
+                input: self.input_aliases.clone(),
+                output: self.output_aliases.clone(),
+                audio: self.audio_aliases.clone(),
+            },
+            defaults: PortsView {
+                input: self.input_defaults.clone(),
+                output: self.output_defaults.clone(),
+                audio: self.audio_defaults.clone(),
+            },
+            ports: PortDetailsView {
+                input: self.input_ports.clone(),
+                output: self.output_ports.clone(),
+                audio: self.audio_ports.clone(),
+            },
         }
+    }
 
-        for (key, _value) in self.output_defaults.iter() {
-            if key.len() > self.longest_str {
-                self.longest_str = key.len()
-            }
+    pub fn routing_view(&self, routing: &[Route]) -> RoutingView {
+        RoutingView {
+            routing: routing
+                .iter()
+                .map(|route| {
+                    let output = self.output_details(route.output);
+                    let input = self.input_details(route.input);
+                    RouteView {
+                        output: route.output,
+                        output_name: output.name.clone(),
+                        output_aliases: output.aliases.clone(),
+                        input: route.input,
+                        input_name: input.name.clone(),
+                        input_aliases: input.aliases.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn edid_view(&self, edid: &[(Input, Edid)]) -> EdidView {
+        EdidView {
+            edid: edid
+                .iter()
+                .map(|(input, edid)| {
+                    let details = self.input_details(*input);
+                    InputEdidView {
+                        input: *input,
+                        input_name: details.name.clone(),
+                        input_aliases: details.aliases.clone(),
+                        edid: *edid,
+                        edid_display: self.display_edid(*edid),
+                    }
+                })
+                .collect(),
         }
+    }
 
-        for (key, _value) in self.output_aliases.iter() {
-            if key.len() > self.longest_str {
-                self.longest_str = key.len()
-            }
+    pub fn audio_view(&self, audio: &[AudioStatus]) -> AudioView {
+        AudioView {
+            audio: audio
+                .iter()
+                .map(|status| OutputAudioView {
+                    output: status.output,
+                    output_display: self.display_audio_target(status.output),
+                    source: status.source,
+                    source_display: self.display_audio_source(status.source),
+                    muted: status.muted,
+                    arc: status.arc,
+                })
+                .collect(),
         }
+    }
+
+    /// Name, aliases and description configured for `input`.
+    pub fn input_details(&self, input: Input) -> &PortDetails {
+        self.input_ports.get(&input).unwrap_or(&NO_DETAILS)
+    }
+
+    /// Name, aliases and description configured for `output`.
+    pub fn output_details(&self, output: Output) -> &PortDetails {
+        self.output_ports.get(&output).unwrap_or(&NO_DETAILS)
+    }
+
+    /// Name, aliases and description configured for the analog `output`.
+    pub fn audio_details(&self, output: AudioOutput) -> &PortDetails {
+        self.audio_ports.get(&output).unwrap_or(&NO_DETAILS)
+    }
 
-        return self.longest_str;
+    /// Every alias configured for `input`.
+    pub fn input_aliases_of(&self, input: Input) -> &[String] {
+        &self.input_details(input).aliases
     }
 
-    pub fn list_input_defaults(&mut self) {
-        if self.longest_str == 0 {
-            self.longest_input_output_key();
+    /// Every alias configured for `output`.
+    pub fn output_aliases_of(&self, output: Output) -> &[String] {
+        &self.output_details(output).aliases
+    }
+
+    /// `input` with its name and aliases, e.g. `hdmiin2 (ps)`.
+    pub fn display_input(&self, input: Input) -> String {
+        self.input_details(input).display(&self.profile.input_name(input))
+    }
+
+    /// `output` with its name and aliases, e.g. `hdmiout4 (tv)`.
+    pub fn display_output(&self, output: Output) -> String {
+        self.output_details(output).display(&self.profile.output_name(output))
+    }
+
+    /// `target` with its name and aliases, e.g. `audioout1 (amp)`.
+    pub fn display_audio_target(&self, target: AudioTarget) -> String {
+        match target {
+            AudioTarget::Hdmi(output) => self.display_output(output),
+            AudioTarget::Analog(output) => self
+                .audio_details(output)
+                .display(output.as_str()),
         }
+    }
 
-        println!("  Input Defaults:");
-        for (key, value) in self.input_defaults.iter() {
-            let spacer_len = self.longest_str - key.len();
-            let mut spacer_string: String = String::new();
-            for _ in 0..spacer_len {
-                spacer_string.push_str(" ")
-            }
-            println!("    {key}: {spacer_string}{value}");
+    /// `edid` with the name and aliases of the output it is copied from, e.g.
+    /// `copy from hdmiout2 (monitor)`.
+    pub fn display_edid(&self, edid: Edid) -> String {
+        match edid {
+            Edid::Preset(preset) => preset.to_string(),
+            Edid::CopyFrom(output) => format!("copy from {}", self.display_output(output)),
         }
     }
-    pub fn list_input_aliases(&mut self) {
-        if self.longest_str == 0 {
-            self.longest_input_output_key();
+
+    /// `source` with the name and aliases of its port, e.g. `hdmiin2 (ps)` or
+    /// `ARC from hdmiout4 (tv)`.
+    pub fn display_audio_source(&self, source: AudioSource) -> String {
+        match source {
+            AudioSource::Follow => source.to_string(),
+            AudioSource::Input(input) => self.display_input(input),
+            AudioSource::Arc(output) => format!("ARC from {}", self.display_output(output)),
         }
+    }
 
-        println!("  Input aliases:");
-        for (key, value) in self.input_aliases.iter() {
-            let spacer_len = self.longest_str - key.len();
-            let mut spacer_string: String = String::new();
-            for _ in 0..spacer_len {
-                spacer_string.push_str(" ")
-            }
-            println!("    {key}: {spacer_string}{value}");
+    /// Loads the labels of `input` and its aliases.
+    pub fn load_input_port(&mut self, input: Input, port: &PortConfiguration) -> Result<()> {
+        if !self.profile.has_input(input) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "input port {} is not supported by the {}",
+                input,
+                self.profile.model()
+            )));
         }
+
+        let details = self.input_ports.entry(input).or_default();
+        details.name = port.name.clone();
+        details.description = port.description.clone();
+        details.disabled = port.disabled;
+        for alias in port.aliases.iter() {
+            self.load_input_alias(alias, input)?;
+        }
+        Ok(())
     }
-    pub fn list_output_defaults(&mut self) {
-        if self.longest_str == 0 {
-            self.longest_input_output_key();
+
+    /// Loads the labels of `output` and its aliases. Only physical outputs have
+    /// labels, `all` can only be aliased.
+    pub fn load_output_port(&mut self, output: Output, port: &PortConfiguration) -> Result<()> {
+        if output == Output::All || !self.profile.has_output(output) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "output port {} is not a physical output of the {}",
+                output,
+                self.profile.model()
+            )));
         }
 
-        println!("  Output Defaults:");
-        for (key, value) in self.output_defaults.iter() {
-            let spacer_len = self.longest_str - key.len();
-            let mut spacer_string: String = String::new();
-            for _ in 0..spacer_len {
-                spacer_string.push_str(" ")
-            }
-            println!("    {key}: {spacer_string}{value}");
+        let details = self.output_ports.entry(output).or_default();
+        details.name = port.name.clone();
+        details.description = port.description.clone();
+        details.disabled = port.disabled;
+        for alias in port.aliases.iter() {
+            self.load_output_alias(alias, output)?;
         }
+        Ok(())
     }
-    pub fn list_output_aliases(&mut self) {
-        if self.longest_str == 0 {
-            self.longest_input_output_key();
+
+    /// Loads the labels of the analog `output` and its aliases.
+    pub fn load_audio_port(&mut self, output: AudioOutput, port: &PortConfiguration) -> Result<()> {
+        if !self.profile.has_audio_output(output) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "audio port {} is not supported by the {}",
+                output,
+                self.profile.model()
+            )));
         }
 
-        println!("  Output aliases:");
-        for (key, value) in self.output_aliases.iter() {
-            let spacer_len = self.longest_str - key.len();
-            let mut spacer_string: String = String::new();
-            for _ in 0..spacer_len {
-                spacer_string.push_str(" ")
-            }
-            println!("    {key}: {spacer_string}{value}");
+        let details = self.audio_ports.entry(output).or_default();
+        details.name = port.name.clone();
+        details.description = port.description.clone();
+        details.disabled = port.disabled;
+        for alias in port.aliases.iter() {
+            self.load_audio_alias(alias, output)?;
         }
+        Ok(())
     }
 
-    pub fn load_input_alias(&mut self, alias: &str, default: &str) -> Result<()> {
-        match is_valid_input(default) {
-            true => {
-                self.input_aliases
-                    .insert(alias.to_string(), default.to_string());
-                return Ok(());
+    /// Adds `alias` for `input`. An alias can only be defined once and can't
+    /// be a built-in port name.
+    pub fn load_input_alias(&mut self, alias: &str, input: Input) -> Result<()> {
+        self.check_new_alias(PortKind::Input, alias, self.input_aliases.contains_key(alias))?;
+        if !self.profile.has_input(input) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "input alias {}: {} is not supported by the {}",
+                alias,
+                input,
+                self.profile.model()
+            )));
+        }
+
+        self.input_aliases.insert(alias.to_string(), input);
+        self.input_ports.entry(input).or_default().aliases.push(alias.to_string());
+        Ok(())
+    }
+
+    /// Adds `alias` for `output`, with the same rules as
+    /// [`Switch::load_input_alias`].
+    pub fn load_output_alias(&mut self, alias: &str, output: Output) -> Result<()> {
+        self.check_new_alias(PortKind::Output, alias, self.output_aliases.contains_key(alias))?;
+        if !self.profile.has_output(output) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "output alias {}: {} is not supported by the {}",
+                alias,
+                output,
+                self.profile.model()
+            )));
+        }
+
+        self.output_aliases.insert(alias.to_string(), output);
+        self.output_ports.entry(output).or_default().aliases.push(alias.to_string());
+        Ok(())
+    }
+
+    /// Adds `alias` for the analog `output`. HDMI and analog outputs are
+    /// named together by `audio`, so an output alias can't be reused here.
+    pub fn load_audio_alias(&mut self, alias: &str, output: AudioOutput) -> Result<()> {
+        self.check_new_alias(PortKind::Audio, alias, self.audio_aliases.contains_key(alias))?;
+        if self.output_aliases.contains_key(alias) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "audio output alias {} is already an output alias",
+                alias
+            )));
+        }
+        if !self.profile.has_audio_output(output) {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "audio output alias {}: {} is not supported by the {}",
+                alias,
+                output,
+                self.profile.model()
+            )));
+        }
+
+        self.audio_aliases.insert(alias.to_string(), output);
+        self.audio_ports.entry(output).or_default().aliases.push(alias.to_string());
+        Ok(())
+    }
+
+    /// Refuses `alias` if it is already defined, hides one of the port names
+    /// of the profile or would be read as an audio source or EDID preset.
+    fn check_new_alias(&self, kind: PortKind, alias: &str, exists: bool) -> Result<()> {
+        let kind = kind.to_string().to_lowercase();
+        if exists {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "{} alias {} is defined more than once",
+                kind, alias
+            )));
+        }
+
+        let builtin = default_port(&self.input_defaults, alias).is_some()
+            || default_port(&self.output_defaults, alias).is_some()
+            || default_port(&self.audio_defaults, alias).is_some();
+        if builtin {
+            return Err(HdmiSwitchError::InvalidConfiguration(format!(
+                "{} alias {} shadows the built-in port name",
+                kind, alias
+            )));
+        }
+
+        check_keyword(alias)
+            .map_err(|err| HdmiSwitchError::InvalidConfiguration(format!("{} {}", kind, err)))
+    }
+
+    /// Adds a scene after checking that every route in it resolves, so a typo
+    /// is reported when the configuration is loaded rather than halfway
+    /// through applying the scene. Disabled ports are only refused when the
+    /// scene is applied.
+    pub fn load_scene(&mut self, name: &str, routes: &[RouteConfiguration]) -> Result<()> {
+        for route in routes.iter() {
+            self.resolve_input(&route.input)
+                .and_then(|_input| self.resolve_output(&route.output))
+                .map_err(|err| {
+                    HdmiSwitchError::InvalidConfiguration(format!("scene {}: {}", name, err))
+                })?;
+        }
+
+        self.scenes.insert(name.to_string(), routes.to_vec());
+        Ok(())
+    }
+
+    /// Routes of the scene called `name`.
+    pub fn scene(&self, name: &str) -> Result<&[RouteConfiguration]> {
+        match self.scenes.get(name) {
+            Some(routes) => Ok(routes),
+            _ => Err(HdmiSwitchError::UnknownScene(name.to_string())),
+        }
+    }
+
+    /// Resolves an input alias or API name to the API name of the input.
+    pub fn resolve_input(&self, input: &str) -> Result<Input> {
+        match self
+            .input_aliases
+            .get(input)
+            .or_else(|| default_port(&self.input_defaults, input))
+        {
+            Some(value) => Ok(*value),
+            _ => Err(HdmiSwitchError::UnknownAlias {
+                kind: PortKind::Input,
+                alias: input.to_string(),
+            }),
+        }
+    }
+
+    /// Resolves an output alias or API name to the API name of the output.
+    pub fn resolve_output(&self, output: &str) -> Result<Output> {
+        match self
+            .output_aliases
+            .get(output)
+            .or_else(|| default_port(&self.output_defaults, output))
+        {
+            Some(value) => Ok(*value),
+            _ => Err(HdmiSwitchError::UnknownAlias {
+                kind: PortKind::Output,
+                alias: output.to_string(),
+            }),
+        }
+    }
+
+    /// Resolves an EDID preset, or an output alias or API name whose sink's
+    /// EDID is to be copied. Aliases can't be named after presets, and disabled
+    /// outputs are refused like by [`Switch::resolve_route`].
+    pub fn resolve_edid(&self, edid: &str) -> Result<Edid> {
+        if let Ok(preset) = edid.parse::<EdidPreset>() {
+            if self.profile.edid_presets().contains(&preset) {
+                return Ok(Edid::Preset(preset));
             }
-            false => {
-                return Err(anyhow!("Loading aliases: Input {} is not a supported input.", default));
+        }
+
+        match self.resolve_output(edid) {
+            Ok(Output::All) | Err(HdmiSwitchError::UnknownAlias { .. }) => {
+                Err(HdmiSwitchError::UnknownEdid(edid.to_string()))
             }
-        };
+            Ok(output) => {
+                self.check_output_enabled(output)?;
+                Ok(Edid::CopyFrom(output))
+            }
+            Err(err) => Err(err),
+        }
     }
 
-    pub fn load_output_alias(&mut self, alias: &str, default: &str) -> Result<()> {
-        match is_valid_output(default) {
-            true => {
-                self.output_aliases
-                    .insert(alias.to_string(), default.to_string());
-                return Ok(());
+    /// Resolves an HDMI output or analog audio output alias or API name.
+    /// Output aliases win, `all` is refused as audio is set per output.
+    pub fn resolve_audio_target(&self, target: &str) -> Result<AudioTarget> {
+        match self.resolve_output(target) {
+            Ok(Output::All) => {
+                return Err(HdmiSwitchError::InvalidPort(
+                    "audio is set one output at a time, all can't be used".to_string(),
+                ));
             }
-            false => {
-                return Err(anyhow!("Loading aliases: Output {} is not a supported output.", default));
-            }
-        };
+            Ok(output) => return Ok(AudioTarget::Hdmi(output)),
+            Err(HdmiSwitchError::UnknownAlias { .. } | HdmiSwitchError::InvalidPort(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        match self
+            .audio_aliases
+            .get(target)
+            .or_else(|| default_port(&self.audio_defaults, target))
+        {
+            Some(output) => Ok(AudioTarget::Analog(*output)),
+            _ => Err(HdmiSwitchError::UnknownAlias {
+                kind: PortKind::Audio,
+                alias: target.to_string(),
+            }),
+        }
     }
-    pub fn command_build(self, input: &str, output: &str) -> Result<String> {
-        let mut inputs: IndexMap<String, String> = IndexMap::new();
-        let mut outputs: IndexMap<String, String> = IndexMap::new();
 
-        inputs.extend(self.input_defaults);
-        inputs.extend(self.input_aliases);
+    /// Resolves `follow`, an input alias or API name, or `arc:` followed by an
+    /// output alias or API name for the audio its display returns over ARC.
+    pub fn resolve_audio_source(&self, source: &str) -> Result<AudioSource> {
+        if source.eq_ignore_ascii_case("follow") {
+            return Ok(AudioSource::Follow);
+        }
 
-        let input = match inputs.get(input) {
-            Some(value) => value,
-            _ => {
-                return Err(anyhow!("Input {} not supported", input));
+        let arc = source
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("arc:"))
+            .map(|_prefix| &source[4..]);
+        match arc {
+            Some(output) => match self.resolve_output(output)? {
+                Output::All => Err(HdmiSwitchError::InvalidPort(
+                    "ARC audio comes from a single output, all can't be used".to_string(),
+                )),
+                output => Ok(AudioSource::Arc(output)),
+            },
+            None => Ok(AudioSource::Input(self.resolve_input(source)?)),
+        }
+    }
+
+    /// Resolves `target` and the `source` it is to play, as by
+    /// [`Switch::resolve_audio_target`] and [`Switch::resolve_audio_source`].
+    /// Disabled ports are refused like by [`Switch::resolve_route`].
+    pub fn resolve_audio_change(
+        &self,
+        target: &str,
+        source: &str,
+    ) -> Result<(AudioTarget, AudioSource)> {
+        let target = self.resolve_audio_target(target)?;
+        let source = self.resolve_audio_source(source)?;
+        self.check_audio_target_enabled(target)?;
+        match source {
+            AudioSource::Input(input) if self.input_details(input).disabled => {
+                return Err(HdmiSwitchError::PortDisabled {
+                    kind: PortKind::Input,
+                    port: self.display_input(input),
+                });
             }
-        };
+            AudioSource::Arc(output) => self.check_output_enabled(output)?,
+            _source => {}
+        }
+
+        Ok((target, source))
+    }
 
-        outputs.extend(self.output_defaults);
-        outputs.extend(self.output_aliases);
+    /// Resolves the output to mute or unmute, refusing disabled ones.
+    pub fn resolve_mute_target(&self, target: &str) -> Result<AudioTarget> {
+        let target = self.resolve_audio_target(target)?;
+        self.check_audio_target_enabled(target)?;
 
-        let output = match outputs.get(output) {
-            Some(value) => value,
-            _ => {
-                return Err(anyhow!("Output {} not supported", output));
+        Ok(target)
+    }
+
+    /// Resolves the output to turn the audio return channel of on or off,
+    /// refusing disabled ones. ARC is set one output at a time.
+    pub fn resolve_arc_output(&self, output: &str) -> Result<Output> {
+        let output = match self.resolve_output(output)? {
+            Output::All => {
+                return Err(HdmiSwitchError::InvalidPort(
+                    "ARC is set one output at a time, all can't be used".to_string(),
+                ));
             }
+            output => output,
         };
+        self.check_output_enabled(output)?;
 
-        let command: String = format!("SET SW {} {}\n\r", input, output);
-        return Ok(command);
+        Ok(output)
     }
-}
 
-// NOTE: should this return an error?
-fn is_valid_input(input: &str) -> bool {
-    match to_hdmi_in(input) {
-        Ok(_) => return true,
-        Err(_) => return false,
+    /// Resolves the route from `input` to `output`, refusing disabled ports.
+    /// Routing to `all` is refused if any output is disabled.
+    pub fn resolve_route(&self, input: &str, output: &str) -> Result<Route> {
+        let input = self.resolve_input(input)?;
+        let output = self.resolve_output(output)?;
+
+        if self.input_details(input).disabled {
+            return Err(HdmiSwitchError::PortDisabled {
+                kind: PortKind::Input,
+                port: self.display_input(input),
+            });
+        }
+        self.check_output_enabled(output)?;
+
+        Ok(Route { input, output })
     }
-}
 
-pub fn to_hdmi_in(input: &str) -> Result<HdmiIn> {
-    match input {
-        HDMIIN1 => return Ok(HDMIIN1),
-        HDMIIN2 => return Ok(HDMIIN2),
-        HDMIIN3 => return Ok(HDMIIN3),
-        HDMIIN4 => return Ok(HDMIIN4),
-        _v => return Err(anyhow!("{} is not a supported HDMI input", input)),
+    /// Builds the command routing `input` to `output`, resolved as by
+    /// [`Switch::resolve_route`].
+    pub fn command_build(&self, input: &str, output: &str) -> Result<String> {
+        let route = self.resolve_route(input, output)?;
+
+        Ok(self.profile.route_command(route.input, route.output))
     }
-}
 
-// NOTE: should this return an error?
-fn is_valid_output(output: &str) -> bool {
-    match to_hdmi_out(output) {
-        Ok(_) => return true,
-        Err(_) => return false,
+    /// Resolves the output `command` is sent to the display of, refusing
+    /// disabled outputs like [`Switch::resolve_route`]. Only `power-off` can
+    /// be sent to `all`.
+    pub fn resolve_cec_output(&self, output: &str, command: CecCommand) -> Result<Output> {
+        let output = self.resolve_output(output)?;
+        if output == Output::All && command != CecCommand::PowerOff {
+            return Err(HdmiSwitchError::InvalidPort(format!(
+                "CEC {} is sent one output at a time, all can only be used with power-off",
+                command
+            )));
+        }
+        self.check_output_enabled(output)?;
+
+        Ok(output)
     }
+
+    /// Fails if `output`, or any output for `all`, is disabled.
+    fn check_output_enabled(&self, output: Output) -> Result<()> {
+        let targets: &[Output] = match output {
+            Output::All => self.profile.outputs(),
+            _ => std::slice::from_ref(&output),
+        };
+        if let Some(disabled) = targets
+            .iter()
+            .find(|target| self.output_details(**target).disabled)
+        {
+            return Err(HdmiSwitchError::PortDisabled {
+                kind: PortKind::Output,
+                port: self.display_output(*disabled),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the HDMI or analog `target` is disabled.
+    fn check_audio_target_enabled(&self, target: AudioTarget) -> Result<()> {
+        match target {
+            AudioTarget::Hdmi(output) => self.check_output_enabled(output),
+            AudioTarget::Analog(output) if self.audio_details(output).disabled => {
+                Err(HdmiSwitchError::PortDisabled {
+                    kind: PortKind::Audio,
+                    port: self.display_audio_target(target),
+                })
+            }
+            AudioTarget::Analog(_output) => Ok(()),
+        }
+    }
+}
+
+/// Looks up an API name in `defaults` without regard to case, the same rule
+/// the port types parse names with.
+fn default_port<'a, T>(defaults: &'a IndexMap<String, T>, name: &str) -> Option<&'a T> {
+    defaults
+        .iter()
+        .find(|(default, _port)| default.eq_ignore_ascii_case(name))
+        .map(|(_default, port)| port)
 }
 
-pub fn to_hdmi_out(output: &str) -> Result<HdmiOut> {
-    match output {
-        HDMIOUT1 => return Ok(HDMIOUT1),
-        HDMIOUT2 => return Ok(HDMIOUT2),
-        HDMIOUT3 => return Ok(HDMIOUT3),
-        HDMIOUT4 => return Ok(HDMIOUT4),
-        _v => return Err(anyhow!("{} is not a suppported HDMI output", output)),
+impl Default for Switch {
+    fn default() -> Self {
+        Self::new()
     }
 }
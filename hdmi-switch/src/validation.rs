@@ -0,0 +1,384 @@
+use crate::configuration::{
+    check_timeout, check_version, Configuration, PortConfiguration, TransportConfiguration,
+};
+use crate::error::HdmiSwitchError;
+use crate::table::{Column, Table};
+use crate::utils::Switch;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Something wrong with a configuration file, at the YAML path of the value
+/// it is about, e.g. `input.aliases.ps`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(path: impl Into<String>, err: HdmiSwitchError) -> Self {
+        let message = match err {
+            HdmiSwitchError::InvalidConfiguration(message) => message,
+            err => err.to_string(),
+        };
+
+        Problem {
+            path: path.into(),
+            message,
+        }
+    }
+}
+
+/// Serializable result of `config validate`.
+#[derive(Serialize, Debug)]
+pub struct ValidationView {
+    pub path: String,
+    pub valid: bool,
+    pub problems: Vec<Problem>,
+}
+
+impl ValidationView {
+    pub fn new(path: &str, problems: Vec<Problem>) -> Self {
+        ValidationView {
+            path: path.to_string(),
+            valid: problems.is_empty(),
+            problems,
+        }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec![Column::new().highlight(), Column::new()]);
+
+        if self.valid {
+            table.heading(0, &format!("{} is valid", self.path));
+            return table;
+        }
+
+        let count = match self.problems.len() {
+            1 => "1 problem".to_string(),
+            count => format!("{} problems", count),
+        };
+        table.heading(0, &format!("{} has {}:", self.path, count));
+        for problem in self.problems.iter() {
+            table.row(2, vec![format!("{}:", problem.path), problem.message.clone()]);
+        }
+
+        table
+    }
+}
+
+/// Checks everything `configuration` is used for without connecting to the
+/// switch. Unlike loading a [`Switch`], which stops at the first mistake,
+/// every problem is returned.
+pub fn validate(configuration: &Configuration) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+
+    if let Err(err) = check_version(configuration.version) {
+        problems.push(Problem::new("version", err));
+    }
+    if configuration.devices.is_empty() {
+        problems.extend(validate_switch(configuration));
+        return problems;
+    }
+
+    if let Some(name) = &configuration.default_device {
+        if !configuration.devices.contains_key(name) {
+            problems.push(Problem::new(
+                "default_device",
+                HdmiSwitchError::UnknownDevice(name.to_string()),
+            ));
+        }
+    }
+    // Each device is checked with the top-level settings it inherits, so a
+    // mistake there is reported once for every device.
+    for name in configuration.device_names() {
+        let device = match configuration.device(Some(name)) {
+            Ok(device) => device,
+            Err(err) => {
+                problems.push(Problem::new(format!("devices.{}", name), err));
+                continue;
+            }
+        };
+        problems.extend(validate_switch(&device).into_iter().map(|problem| Problem {
+            path: format!("devices.{}.{}", name, problem.path),
+            ..problem
+        }));
+    }
+
+    problems
+}
+
+/// Keys repeated in a mapping of the YAML `text`, which reading it into a
+/// [`Configuration`] leniently drops. Text that isn't YAML has none, reading
+/// it reports the error.
+pub fn duplicate_keys(text: &str) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+    if let Ok(node) = serde_yaml::from_str::<Node>(text) {
+        node.collect_duplicates("", &mut problems);
+    }
+    problems
+}
+
+/// A YAML document keeping every key of its mappings, repeated ones included.
+enum Node {
+    Mapping(Vec<(String, Node)>),
+    Sequence(Vec<Node>),
+    Scalar,
+}
+
+impl Node {
+    fn collect_duplicates(&self, path: &str, problems: &mut Vec<Problem>) {
+        match self {
+            Node::Mapping(entries) => {
+                for (position, (key, node)) in entries.iter().enumerate() {
+                    let path = match path {
+                        "" => key.clone(),
+                        path => format!("{}.{}", path, key),
+                    };
+                    if entries[..position].iter().any(|(seen, _node)| seen == key) {
+                        problems.push(Problem::new(
+                            path.as_str(),
+                            HdmiSwitchError::InvalidConfiguration(format!(
+                                "{} is defined more than once",
+                                key
+                            )),
+                        ));
+                    }
+                    node.collect_duplicates(&path, problems);
+                }
+            }
+            Node::Sequence(nodes) => {
+                for (position, node) in nodes.iter().enumerate() {
+                    node.collect_duplicates(&format!("{}[{}]", path, position), problems);
+                }
+            }
+            Node::Scalar => {}
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a YAML value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _value: bool) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_i64<E: de::Error>(self, _value: i64) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_u64<E: de::Error>(self, _value: u64) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_f64<E: de::Error>(self, _value: f64) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_str<E: de::Error>(self, _value: &str) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Node, E> {
+        Ok(Node::Scalar)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        Node::deserialize(deserializer)
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Node, S::Error> {
+        let mut nodes: Vec<Node> = Vec::new();
+        while let Some(node) = access.next_element()? {
+            nodes.push(node);
+        }
+        Ok(Node::Sequence(nodes))
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Node, M::Error> {
+        let mut entries: Vec<(String, Node)> = Vec::new();
+        while let Some(key) = access.next_key::<Value>()? {
+            let key = match key {
+                Value::String(key) => key,
+                key => serde_yaml::to_string(&key)
+                    .map(|key| key.trim_end().to_string())
+                    .map_err(de::Error::custom)?,
+            };
+            entries.push((key, access.next_value()?));
+        }
+        Ok(Node::Mapping(entries))
+    }
+}
+
+/// Checks the settings of a single switch.
+fn validate_switch(configuration: &Configuration) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+    let server = &configuration.server;
+
+    match configuration.get_transport() {
+        TransportConfiguration::Serial { path, baud_rate, .. } => {
+            if path.is_empty() {
+                problems.push(Problem::new(
+                    "server.transport.path",
+                    HdmiSwitchError::InvalidConfiguration("path must not be empty".to_string()),
+                ));
+            }
+            if baud_rate == Some(0) {
+                problems.push(Problem::new(
+                    "server.transport.baud_rate",
+                    HdmiSwitchError::InvalidConfiguration("baud rate must be positive".to_string()),
+                ));
+            }
+        }
+        TransportConfiguration::Telnet | TransportConfiguration::RawTcp { .. } => {
+            if let Err(message) = check_host(&server.host) {
+                problems.push(Problem::new(
+                    "server.host",
+                    HdmiSwitchError::InvalidConfiguration(message),
+                ));
+            }
+        }
+    }
+    if server.port == Some(0) {
+        problems.push(Problem::new(
+            "server.port",
+            HdmiSwitchError::InvalidConfiguration("port must be between 1 and 65535".to_string()),
+        ));
+    }
+    for (path, timeout) in [
+        ("server.connect_timeout", server.connect_timeout),
+        ("server.read_timeout", server.read_timeout),
+    ] {
+        if let Some(Err(err)) = timeout.map(check_timeout) {
+            problems.push(Problem::new(path, err));
+        }
+    }
+
+    let mut switch = Switch::with_profile(configuration.get_model().profile());
+    for (alias, input) in configuration.input.aliases.iter() {
+        if let Err(err) = switch.load_input_alias(alias, *input) {
+            problems.push(Problem::new(format!("input.aliases.{}", alias), err));
+        }
+    }
+    for (input, port) in configuration.input.ports.iter() {
+        let path = format!("input.ports.{}", input);
+        if let Err(err) = switch.load_input_port(*input, &without_aliases(port)) {
+            problems.push(Problem::new(path, err));
+            continue;
+        }
+        for (position, alias) in port.aliases.iter().enumerate() {
+            if let Err(err) = switch.load_input_alias(alias, *input) {
+                problems.push(Problem::new(format!("{}.aliases[{}]", path, position), err));
+            }
+        }
+    }
+    for (alias, output) in configuration.output.aliases.iter() {
+        if let Err(err) = switch.load_output_alias(alias, *output) {
+            problems.push(Problem::new(format!("output.aliases.{}", alias), err));
+        }
+    }
+    for (output, port) in configuration.output.ports.iter() {
+        let path = format!("output.ports.{}", output);
+        if let Err(err) = switch.load_output_port(*output, &without_aliases(port)) {
+            problems.push(Problem::new(path, err));
+            continue;
+        }
+        for (position, alias) in port.aliases.iter().enumerate() {
+            if let Err(err) = switch.load_output_alias(alias, *output) {
+                problems.push(Problem::new(format!("{}.aliases[{}]", path, position), err));
+            }
+        }
+    }
+    for (alias, output) in configuration.audio.aliases.iter() {
+        if let Err(err) = switch.load_audio_alias(alias, *output) {
+            problems.push(Problem::new(format!("audio.aliases.{}", alias), err));
+        }
+    }
+    for (output, port) in configuration.audio.ports.iter() {
+        let path = format!("audio.ports.{}", output);
+        if let Err(err) = switch.load_audio_port(*output, &without_aliases(port)) {
+            problems.push(Problem::new(path, err));
+            continue;
+        }
+        for (position, alias) in port.aliases.iter().enumerate() {
+            if let Err(err) = switch.load_audio_alias(alias, *output) {
+                problems.push(Problem::new(format!("{}.aliases[{}]", path, position), err));
+            }
+        }
+    }
+
+    for (name, routes) in configuration.scenes.iter() {
+        if routes.is_empty() {
+            problems.push(Problem::new(
+                format!("scenes.{}", name),
+                HdmiSwitchError::InvalidConfiguration("scene has no routes".to_string()),
+            ));
+        }
+        // Disabled ports are only refused when the scene is applied, as by
+        // Switch::load_scene.
+        for (position, route) in routes.iter().enumerate() {
+            let path = format!("scenes.{}[{}]", name, position);
+            if let Err(err) = switch.resolve_input(&route.input) {
+                problems.push(Problem::new(format!("{}.input", path), err));
+            }
+            if let Err(err) = switch.resolve_output(&route.output) {
+                problems.push(Problem::new(format!("{}.output", path), err));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Checks that `host` is an IP address or a syntactically valid host name.
+pub fn check_host(host: &str) -> Result<(), String> {
+    if host.is_empty() {
+        return Err("host must not be empty".to_string());
+    }
+    if host.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    let name = host.strip_suffix('.').unwrap_or(host);
+    let valid = name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        return Err(format!("{} is not a valid host name or IP address", host));
+    }
+
+    Ok(())
+}
+
+/// `port` with its aliases left out, so they can be checked one by one.
+fn without_aliases(port: &PortConfiguration) -> PortConfiguration {
+    PortConfiguration {
+        aliases: Vec::new(),
+        ..port.clone()
+    }
+}
@@ -0,0 +1,237 @@
+use crate::client::SwitchClient;
+use crate::configuration::{
+    check_alias, AudioConfiguration, Configuration, InputConfiguration, OutputConfiguration,
+    ServerConfiguration, CONFIGURATION_VERSION,
+};
+use crate::device::Model;
+use crate::error::{HdmiSwitchError, Result};
+use crate::validation::check_host;
+use indexmap::IndexMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// Interactive setup behind `config init`. Questions are written to `output`
+/// and answered one line at a time from `input`, so tests can script it.
+pub struct Wizard<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Wizard<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Wizard { input, output }
+    }
+
+    /// Asks for the switch address and an alias for every port, optionally
+    /// checking that the switch answers before going on.
+    pub fn run(&mut self) -> Result<Configuration> {
+        let model = Model::default();
+        let profile = model.profile();
+
+        let host = self.ask_until("Host name or IP address of the HDMI switch", None, |host| {
+            check_host(host).map(|_ok| host.to_string())
+        })?;
+        let port = self.ask_until("Telnet port", Some("23"), |port| match port.parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(format!("{} is not a port number between 1 and 65535", port)),
+        })?;
+
+        let mut configuration = Configuration {
+            version: CONFIGURATION_VERSION,
+            default_device: None,
+            server: ServerConfiguration {
+                host,
+                port: Some(port),
+                connect_timeout: None,
+                read_timeout: None,
+                model: Some(model),
+                transport: None,
+            },
+            input: InputConfiguration::default(),
+            output: OutputConfiguration::default(),
+            audio: AudioConfiguration::default(),
+            scenes: IndexMap::new(),
+            devices: IndexMap::new(),
+        };
+
+        if self.confirm("Check that the switch is reachable now?", true)? {
+            self.check_reachable(&configuration)?;
+        }
+
+        self.say("Name each port, leave the name empty to skip it.")?;
+        let mut names: Vec<String> = Vec::new();
+        for input in profile.inputs() {
+            let name = profile.input_name(*input);
+            if let Some(alias) = self.ask_alias(&name, &names)? {
+                configuration.input.aliases.insert(alias.clone(), *input);
+                names.push(alias);
+            }
+        }
+        for output in profile.outputs() {
+            let name = profile.output_name(*output);
+            if let Some(alias) = self.ask_alias(&name, &names)? {
+                configuration.output.aliases.insert(alias.clone(), *output);
+                names.push(alias);
+            }
+        }
+
+        Ok(configuration)
+    }
+
+    fn check_reachable(&mut self, configuration: &Configuration) -> Result<()> {
+        match SwitchClient::connect(configuration) {
+            Ok(_client) => self.say(&format!(
+                "Connected to {}:{}.",
+                configuration.server.host,
+                configuration.get_port()
+            )),
+            Err(err) => {
+                let mut message = format!("Couldn't reach the switch: {}", err);
+                if let Some(source) = std::error::Error::source(&err) {
+                    message.push_str(&format!(": {}", source));
+                }
+                self.say(&message)?;
+                if self.confirm("Write the configuration anyway?", false)? {
+                    return Ok(());
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Asks for an alias of the port called `name`. Aliases are kept unique
+    /// across inputs and outputs so the file reads unambiguously.
+    fn ask_alias(&mut self, name: &str, taken: &[String]) -> Result<Option<String>> {
+        self.ask_until(&format!("Name for {}", name), Some(""), |alias| {
+            if alias.is_empty() {
+                return Ok(None);
+            }
+            if taken.iter().any(|taken| taken == alias) {
+                return Err(format!("{} is already used", alias));
+            }
+            if alias.chars().any(|c| c.is_whitespace() || c == ':') {
+                return Err("names can't contain spaces or colons".to_string());
+            }
+            check_alias(alias)?;
+
+            Ok(Some(alias.to_string()))
+        })
+    }
+
+    /// Repeats `question` until `parse` accepts the answer. An empty answer is
+    /// replaced with `default` when there is one.
+    fn ask_until<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T> {
+        loop {
+            let prompt = match default {
+                Some(default) if !default.is_empty() => format!("{} [{}]: ", question, default),
+                _ => format!("{}: ", question),
+            };
+            let answer = self.ask(&prompt)?;
+            let answer = match (answer.is_empty(), default) {
+                (true, Some(default)) => default.to_string(),
+                _ => answer,
+            };
+
+            match parse(&answer) {
+                Ok(value) => return Ok(value),
+                Err(message) => self.say(&message)?,
+            }
+        }
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        self.ask_until(&format!("{} [{}]", question, hint), Some(""), |answer| {
+            match answer.to_lowercase().as_str() {
+                "" => Ok(default),
+                "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err("Please answer y or n".to_string()),
+            }
+        })
+    }
+
+    fn ask(&mut self, prompt: &str) -> Result<String> {
+        write!(self.output, "{}", prompt)
+            .and_then(|_ok| self.output.flush())
+            .map_err(|source| HdmiSwitchError::Console {
+                action: "write the question".to_string(),
+                source,
+            })?;
+
+        let mut answer = String::new();
+        match self.input.read_line(&mut answer) {
+            Ok(0) => Err(HdmiSwitchError::Usage(
+                "Setup was cancelled before it finished".to_string(),
+            )),
+            Ok(_length) => Ok(answer.trim().to_string()),
+            Err(source) => Err(HdmiSwitchError::Console {
+                action: "read the answer".to_string(),
+                source,
+            }),
+        }
+    }
+
+    fn say(&mut self, message: &str) -> Result<()> {
+        writeln!(self.output, "{}", message).map_err(|source| HdmiSwitchError::Console {
+            action: "write to standard output".to_string(),
+            source,
+        })
+    }
+}
+
+/// Fails if there is a file at `path` and `force` isn't set.
+pub fn check_overwrite(path: &str, force: bool) -> Result<()> {
+    if !force && Path::new(path).exists() {
+        return Err(already_exists(path));
+    }
+
+    Ok(())
+}
+
+fn already_exists(path: &str) -> HdmiSwitchError {
+    HdmiSwitchError::Usage(format!("{} already exists, use --force to overwrite it", path))
+}
+
+/// Writes `configuration` to `path` as YAML, creating its directory. Fails
+/// if there is a file at `path` and `force` isn't set.
+pub fn write_configuration(path: &str, configuration: &Configuration, force: bool) -> Result<()> {
+    let yaml = serde_yaml::to_string(configuration)
+        .map_err(|err| HdmiSwitchError::Render(err.to_string()))?;
+    let contents = format!(
+        "# Written by `hdmi-switch config init`, see example/configuration/configuration.yaml\n\
+         # in the hdmi-switch repository for every option.\n{}",
+        yaml
+    );
+
+    match create_file(Path::new(path), &contents, force) {
+        Ok(()) => Ok(()),
+        // Checked again on creation, the file may have appeared while the
+        // questions were answered.
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Err(already_exists(path)),
+        Err(source) => Err(HdmiSwitchError::ConfigWrite {
+            path: path.to_string(),
+            source,
+        }),
+    }
+}
+
+fn create_file(path: &Path, contents: &str, force: bool) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!force)
+        .open(path)?;
+    file.write_all(contents.as_bytes())
+}
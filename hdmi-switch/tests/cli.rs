@@ -0,0 +1,1165 @@
+mod common;
+
+use common::{hdmi_switch, hdmi_switch_discovering, hdmi_switch_with_stdin, MockSwitch};
+use hdmi_switch::audio::AudioTarget;
+use hdmi_switch::port::AudioOutput;
+use hdmi_switch::{Input, Output};
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::Path;
+use tempfile::{tempdir, NamedTempFile};
+
+const ALIASES: &str = "\
+input:
+  aliases:
+    pc: hdmiin1
+    ps: hdmiin2
+output:
+  aliases:
+    monitor: hdmiout1
+    tv: hdmiout4
+";
+
+#[test]
+fn switch_routes_alias() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "-i", "ps", "-o", "tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.commands(), vec!["SET SW hdmiin2 hdmiout4"]);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+}
+
+#[test]
+fn switch_routes_all_outputs() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "-i", "hdmiin3", "-o", "all"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    for port in Output::PORTS {
+        assert_eq!(mock.route(port), Input::Hdmi3);
+    }
+}
+
+#[test]
+fn switch_sends_multiple_routes_in_one_session() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(
+        configuration.path(),
+        &["switch", "ps:tv", "pc:monitor", "-i", "hdmiin4", "-o", "hdmiout2"],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        mock.commands(),
+        vec![
+            "SET SW hdmiin2 hdmiout4",
+            "SET SW hdmiin1 hdmiout1",
+            "SET SW hdmiin4 hdmiout2",
+        ]
+    );
+}
+
+#[test]
+fn switch_with_unknown_alias_sends_nothing() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:tv", "xbox:tv"]);
+
+    assert_eq!(output.status.code(), Some(20));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("xbox"));
+    assert!(mock.commands().is_empty());
+}
+
+#[test]
+fn switch_reports_rejected_command() {
+    let mock = MockSwitch::start();
+    mock.set_failing(true);
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:tv"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Command FAILED"));
+}
+
+#[test]
+fn banner_split_across_reads_is_not_taken_for_the_acknowledgement() {
+    let mock = MockSwitch::start();
+    mock.set_split_banner(true);
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+}
+
+#[test]
+fn status_prints_routing_with_aliases() {
+    let mock = MockSwitch::start();
+    mock.set_route(Input::Hdmi2, Output::Hdmi4);
+    mock.set_route(Input::Hdmi3, Output::Hdmi2);
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["status"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\
+Routing:
+  hdmiout1 (monitor): hdmiin1 (pc)
+  hdmiout2:           hdmiin3
+  hdmiout3:           hdmiin1 (pc)
+  hdmiout4 (tv):      hdmiin2 (ps)
+"
+    );
+}
+
+#[test]
+fn scene_applies_every_route() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(&format!(
+        "{}scenes:\n  movie-night:\n    - input: ps\n      output: tv\n    - input: pc\n      output: monitor\n",
+        ALIASES
+    ));
+
+    let output = hdmi_switch(configuration.path(), &["scene", "movie-night"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+    assert_eq!(mock.route(Output::Hdmi1), Input::Hdmi1);
+    assert_eq!(mock.commands().len(), 2);
+}
+
+#[test]
+fn unreachable_switch_exits_with_connection_error() {
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = unused.local_addr().unwrap().port();
+    drop(unused);
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(configuration, "server:\n  host: 127.0.0.1\n  port: {}\n{}", port, ALIASES).unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["status"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Couldn't connect"));
+}
+
+#[test]
+fn unknown_names_are_reported_without_connecting() {
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = unused.local_addr().unwrap().port();
+    drop(unused);
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(configuration, "server:\n  host: 127.0.0.1\n  port: {}\n{}", port, ALIASES).unwrap();
+
+    for (args, code) in [
+        (&["switch", "typo:tv"][..], 20),
+        (&["scene", "nope"][..], 21),
+        (&["cec", "power-on", "nope"][..], 20),
+        (&["edid", "set", "pc", "nonsense"][..], 25),
+        (&["audio", "set", "nope", "follow"][..], 20),
+    ] {
+        let output = hdmi_switch(configuration.path(), args);
+
+        assert_eq!(output.status.code(), Some(code), "{:?}", args);
+    }
+}
+
+#[test]
+fn silent_switch_times_out() {
+    let mock = MockSwitch::start();
+    mock.set_silent(true);
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(
+        configuration.path(),
+        &["--read-timeout", "0.2", "switch", "ps:tv"],
+    );
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Timed out after 0.2s"));
+}
+
+#[test]
+fn missing_configuration_exits_with_config_error() {
+    let output = hdmi_switch(Path::new("/nonexistent/configuration.yaml"), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[test]
+fn invalid_configuration_reports_position() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "server:\n  host: 127.0.0.1\ninput:\n  aliases:\n    ps: hdmiin9\noutput:\n  aliases: {{}}\n"
+    )
+    .unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 5"));
+}
+
+#[test]
+fn ls_prints_json() {
+    let configuration = MockSwitch::start().configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "ls"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["aliases"]["input"]["ps"], "hdmiin2");
+    assert_eq!(document["aliases"]["output"]["tv"], "hdmiout4");
+    assert_eq!(document["defaults"]["output"]["all"], "all");
+}
+
+#[test]
+fn status_prints_yaml() {
+    let mock = MockSwitch::start();
+    mock.set_route(Input::Hdmi2, Output::Hdmi4);
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "yaml", "status"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_yaml::Value = serde_yaml::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["routing"][3]["output"], "hdmiout4");
+    assert_eq!(document["routing"][3]["output_aliases"][0], "tv");
+    assert_eq!(document["routing"][3]["input"], "hdmiin2");
+    assert_eq!(document["routing"][3]["input_aliases"][0], "ps");
+}
+
+#[test]
+fn ls_lists_every_alias_of_a_port() {
+    let configuration = MockSwitch::start().configuration(&format!("{}    tele: hdmiout4\n", ALIASES));
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "ls"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        document["ports"]["output"]["hdmiout4"]["aliases"],
+        serde_json::json!(["tv", "tele"])
+    );
+    assert_eq!(document["ports"]["output"]["hdmiout2"]["aliases"], serde_json::json!([]));
+}
+
+const PORTS: &str = "\
+input:
+  aliases:
+    pc: hdmiin1
+  ports:
+    hdmiin2:
+      name: PlayStation
+      aliases: [ps, playstation]
+      description: Living room PS5
+    hdmiin3:
+      aliases: [rack]
+      description: Rack PC, DP to HDMI adapter
+      disabled: true
+output:
+  ports:
+    hdmiout4:
+      name: TV
+      aliases: [tv]
+";
+
+#[test]
+fn status_prints_port_names() {
+    let mock = MockSwitch::start();
+    mock.set_route(Input::Hdmi2, Output::Hdmi4);
+    let configuration = mock.configuration(PORTS);
+
+    let output = hdmi_switch(configuration.path(), &["status"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("hdmiout4 TV (tv): hdmiin2 PlayStation (ps, playstation)"));
+}
+
+#[test]
+fn switch_refuses_disabled_port() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(PORTS);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "pc:tv", "rack:tv"]);
+
+    assert_eq!(output.status.code(), Some(23));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("hdmiin3 (rack) is disabled"));
+    assert!(mock.commands().is_empty());
+}
+
+#[test]
+fn ls_keeps_alias_order() {
+    let configuration = MockSwitch::start().configuration(
+        "input:\n  aliases:\n    xbox: hdmiin3\n    pc: hdmiin1\n    ps: hdmiin2\noutput: {}\n",
+    );
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with(
+        "Aliases:\n  Input aliases:\n    xbox:     hdmiin3 (xbox)\n    pc:       hdmiin1 (pc)\n    \
+         ps:       hdmiin2 (ps)\n"
+    ));
+}
+
+#[test]
+fn duplicate_alias_reports_position() {
+    let configuration = MockSwitch::start().configuration(
+        "input:\n  aliases:\n    ps: hdmiin2\n    pc: hdmiin1\n    ps: hdmiin3\noutput: {}\n",
+    );
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ps is defined more than once"), "{}", stderr);
+    assert!(stderr.contains("line 8"), "{}", stderr);
+}
+
+#[test]
+fn alias_repeated_under_ports_reports_position() {
+    let configuration = MockSwitch::start().configuration(
+        "input:\n  aliases:\n    ps: hdmiin2\n  ports:\n    hdmiin3:\n      aliases: [xbox, ps]\n\
+         output: {}\n",
+    );
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ps is defined more than once"), "{}", stderr);
+    assert!(stderr.contains("line 9"), "{}", stderr);
+}
+
+#[test]
+fn alias_shadowing_builtin_name_is_refused() {
+    let configuration = MockSwitch::start()
+        .configuration("input: {}\noutput:\n  ports:\n    hdmiout1:\n      aliases: [monitor, all]\n");
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("alias all shadows the built-in port name"), "{}", stderr);
+}
+
+#[test]
+fn config_validate_reports_every_problem() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "\
+server:
+  host: not a host
+  read_timeout: -1
+input:
+  aliases:
+    ps: hdmiin2
+  ports:
+    hdmiin3:
+      aliases: [ps]
+      disabled: true
+output: {{}}
+scenes:
+  movie-night:
+    - input: xbox
+      output: hdmiout1
+    - input: hdmiin3
+      output: hdmiout2
+"
+    )
+    .unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "config", "validate"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let paths: Vec<&str> = document["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|problem| problem["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "server.host",
+            "server.read_timeout",
+            "input.ports.hdmiin3.aliases[0]",
+            "scenes.movie-night[0].input",
+        ]
+    );
+}
+
+#[test]
+fn config_validate_reports_every_alias_problem() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "\
+server:
+  host: 192.0.2.1
+input:
+  aliases:
+    ps: hdmiin2
+    ps: hdmiin1
+    hdmiin4: hdmiin3
+output:
+  ports:
+    hdmiout1:
+      aliases: [monitor, monitor]
+"
+    )
+    .unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "config", "validate"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let paths: Vec<&str> = document["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|problem| problem["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "input.aliases.ps",
+            "input.aliases.hdmiin4",
+            "output.ports.hdmiout1.aliases[1]",
+        ]
+    );
+}
+
+#[test]
+fn audio_source_keywords_are_not_aliases() {
+    let configuration = MockSwitch::start().configuration(
+        "input:\n  aliases:\n    follow: hdmiin2\noutput:\n  aliases:\n    arc:tv: hdmiout4\n",
+    );
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("alias follow is reserved"), "{}", stderr);
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "config", "validate"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let paths: Vec<&str> = document["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|problem| problem["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, vec!["input.aliases.follow", "output.aliases.arc:tv"]);
+}
+
+#[test]
+fn edid_presets_are_not_aliases() {
+    let configuration =
+        MockSwitch::start().configuration("output:\n  aliases:\n    1080p_6ch: hdmiout2\n");
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("alias 1080p_6ch is the name of an EDID preset"), "{}", stderr);
+}
+
+#[test]
+fn config_validate_does_not_connect() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(configuration, "server:\n  host: 192.0.2.1\n{}", ALIASES).unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["config", "validate"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is valid"));
+}
+
+#[test]
+fn config_init_writes_configuration() {
+    let mock = MockSwitch::start();
+    let directory = tempdir().unwrap();
+    let path = directory.path().join("hdmi-switch/configuration.yaml");
+    let answers = format!(
+        "127.0.0.1\n{}\ny\npc\nps\n\n\nmonitor\npc\nhdmiout2\n\n\ntv\n",
+        mock.port
+    );
+
+    let output = hdmi_switch_with_stdin(&path, &["config", "init"], &answers);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Connected to 127.0.0.1"), "{}", stdout);
+    assert!(stdout.contains("pc is already used"), "{}", stdout);
+    assert!(stdout.contains("alias hdmiout2 shadows the built-in port name"), "{}", stdout);
+
+    let output = hdmi_switch(&path, &["--output-format", "json", "ls"]);
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["aliases"]["input"]["ps"], "hdmiin2");
+    assert_eq!(document["aliases"]["output"]["monitor"], "hdmiout1");
+    assert_eq!(document["aliases"]["output"]["tv"], "hdmiout4");
+
+    let output = hdmi_switch(&path, &["switch", "ps:tv"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+}
+
+#[test]
+fn config_init_refuses_to_overwrite() {
+    let configuration = MockSwitch::start().configuration(ALIASES);
+    let before = std::fs::read_to_string(configuration.path()).unwrap();
+
+    let output = hdmi_switch_with_stdin(configuration.path(), &["config", "init"], "");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert_eq!(std::fs::read_to_string(configuration.path()).unwrap(), before);
+
+    let output = hdmi_switch_with_stdin(
+        configuration.path(),
+        &["config", "init", "--force"],
+        "10.0.0.5\n\nn\n\n\n\n\n\n\n\n\n",
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    let after = std::fs::read_to_string(configuration.path()).unwrap();
+    assert!(after.contains("host: 10.0.0.5"), "{}", after);
+    assert!(after.contains("port: 23"), "{}", after);
+}
+
+#[test]
+fn configuration_layers_are_merged() {
+    let mock = MockSwitch::start();
+    let home = tempdir().unwrap();
+    let xdg = home.path().join("xdg");
+    std::fs::create_dir_all(xdg.join("hdmi-switch")).unwrap();
+    std::fs::write(
+        xdg.join("hdmi-switch/configuration.yaml"),
+        format!("server:\n  host: 192.0.2.1\n  port: 1\n{}", ALIASES),
+    )
+    .unwrap();
+    let project = home.path().join("project");
+    std::fs::create_dir_all(project.join("nested")).unwrap();
+    std::fs::write(
+        project.join(".hdmi-switch.yaml"),
+        format!("server:\n  port: {}\n", mock.port),
+    )
+    .unwrap();
+    let environment = [
+        ("XDG_CONFIG_HOME", xdg.to_str().unwrap()),
+        ("HDMI_SWITCH_HOST", "127.0.0.1"),
+    ];
+    let directory = project.join("nested");
+
+    let output = hdmi_switch_discovering(&directory, &environment, &["switch", "ps:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+
+    let output = hdmi_switch_discovering(
+        &directory,
+        &environment,
+        &["--output-format", "json", "--read-timeout", "2", "config", "show", "--resolved"],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let source = |path: &str| -> String {
+        let values = document["values"].as_array().unwrap();
+        let value = values.iter().find(|value| value["path"] == path).unwrap();
+        value["source"].as_str().unwrap().to_string()
+    };
+    assert_eq!(source("server.host"), "$HDMI_SWITCH_HOST");
+    assert!(source("server.port").ends_with(".hdmi-switch.yaml"));
+    assert!(source("input.aliases.ps").ends_with("hdmi-switch/configuration.yaml"));
+    assert_eq!(source("server.read_timeout"), "--read-timeout");
+    assert_eq!(source("server.connect_timeout"), "default");
+}
+
+#[test]
+fn later_files_move_aliases_to_other_ports() {
+    let mock = MockSwitch::start();
+    let home = tempdir().unwrap();
+    let xdg = home.path().join("xdg");
+    std::fs::create_dir_all(xdg.join("hdmi-switch")).unwrap();
+    std::fs::write(
+        xdg.join("hdmi-switch/configuration.yaml"),
+        format!("server:\n  host: 127.0.0.1\n  port: {}\n{}", mock.port, ALIASES),
+    )
+    .unwrap();
+    std::fs::write(
+        home.path().join(".hdmi-switch.yaml"),
+        "input:\n  ports:\n    hdmiin3:\n      aliases: [pc]\n",
+    )
+    .unwrap();
+    let environment = [("XDG_CONFIG_HOME", xdg.to_str().unwrap())];
+
+    let output = hdmi_switch_discovering(home.path(), &environment, &["switch", "pc:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi3);
+}
+
+#[test]
+fn merged_configuration_errors_point_at_the_file() {
+    let directory = tempdir().unwrap();
+    std::fs::write(
+        directory.path().join(".hdmi-switch.yaml"),
+        "server:\n  port: 23\ninput:\n  alias:\n    pc: hdmiin1\n",
+    )
+    .unwrap();
+
+    let output = hdmi_switch_discovering(
+        directory.path(),
+        &[("HDMI_SWITCH_HOST", "127.0.0.1")],
+        &["ls"],
+    );
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(".hdmi-switch.yaml: input: unknown field `alias`"), "{}", stderr);
+    assert!(stderr.contains("line 4"), "{}", stderr);
+}
+
+#[test]
+fn configuration_without_port_sections_loads() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration("");
+
+    let output = hdmi_switch(configuration.path(), &["switch", "hdmiin2:hdmiout3"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.route(Output::Hdmi3), Input::Hdmi2);
+}
+
+#[test]
+fn misspelled_key_suggests_the_right_one() {
+    let configuration = MockSwitch::start().configuration("input:\n  alias:\n    pc: hdmiin1\n");
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown field `alias`, did you mean `aliases`?"), "{}", stderr);
+    assert!(stderr.contains("line 5"), "{}", stderr);
+}
+
+#[test]
+fn newer_configuration_version_is_refused() {
+    let configuration = MockSwitch::start().configuration("version: 2\n");
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("version 2 is not supported"));
+}
+
+#[test]
+fn devices_are_selected_by_name() {
+    let living_room = MockSwitch::start();
+    let office = MockSwitch::start();
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "\
+default_device: living-room
+server:
+  host: 127.0.0.1
+{}devices:
+  living-room:
+    server:
+      port: {}
+    output:
+      aliases:
+        projector: hdmiout2
+  office:
+    server:
+      port: {}
+    input:
+      aliases:
+        laptop: hdmiin3
+",
+        ALIASES, living_room.port, office.port
+    )
+    .unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:projector"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(living_room.route(Output::Hdmi2), Input::Hdmi2);
+    assert!(office.commands().is_empty());
+
+    let output = hdmi_switch(configuration.path(), &["--device", "office", "switch", "laptop:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(office.route(Output::Hdmi4), Input::Hdmi3);
+    assert_eq!(living_room.commands(), vec!["SET SW hdmiin2 hdmiout2"]);
+
+    let output = hdmi_switch(configuration.path(), &["--device", "attic", "status"]);
+
+    assert_eq!(output.status.code(), Some(24));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Device attic not found"));
+}
+
+#[test]
+fn library_connects_to_the_default_device() {
+    let living_room = MockSwitch::start();
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "server:\n  host: 127.0.0.1\n{}devices:\n  living-room:\n    server:\n      port: {}\n    \
+         output:\n      aliases:\n        projector: hdmiout2\n",
+        ALIASES, living_room.port
+    )
+    .unwrap();
+    let path = configuration.path().to_string_lossy().to_string();
+    let configuration = hdmi_switch::configuration::get_configuration(path).unwrap();
+
+    let mut client = hdmi_switch::SwitchClient::connect(&configuration).unwrap();
+    client.route("ps", "projector").unwrap();
+
+    assert_eq!(living_room.route(Output::Hdmi2), Input::Hdmi2);
+}
+
+#[test]
+fn device_aliases_replace_top_level_ones() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "\
+server:
+  host: 192.0.2.1
+input:
+  aliases:
+    ps: hdmiin2
+  ports:
+    hdmiin3:
+      aliases: [xbox]
+devices:
+  office:
+    input:
+      aliases:
+        xbox: hdmiin4
+      ports:
+        hdmiin1:
+          aliases: [ps]
+"
+    )
+    .unwrap();
+
+    let output = hdmi_switch(
+        configuration.path(),
+        &["--output-format", "json", "--device", "office", "ls"],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["aliases"]["input"]["ps"], "hdmiin1");
+    assert_eq!(document["aliases"]["input"]["xbox"], "hdmiin4");
+    assert_eq!(document["ports"]["input"]["hdmiin3"]["aliases"], serde_json::json!([]));
+}
+
+#[test]
+fn unknown_default_device_is_refused() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "default_device: attic\ndevices:\n  office:\n    server:\n      host: 192.0.2.1\n"
+    )
+    .unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert_eq!(output.status.code(), Some(24));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Device attic not found"));
+}
+
+#[test]
+fn ls_lists_every_device() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(
+        configuration,
+        "\
+devices:
+  living-room:
+    server:
+      host: 192.0.2.1
+{}  office:
+    server:
+      host: 192.0.2.2
+      port: 2323
+",
+        ALIASES.lines().map(|line| format!("    {}\n", line)).collect::<String>()
+    )
+    .unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "ls"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let devices = document["devices"].as_object().unwrap();
+    assert_eq!(devices.keys().collect::<Vec<_>>(), vec!["living-room", "office"]);
+    assert_eq!(devices["living-room"]["aliases"]["input"]["ps"], "hdmiin2");
+    assert_eq!(devices["office"]["address"], "192.0.2.2:2323");
+    assert!(devices["office"]["aliases"]["input"].get("ps").is_none());
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("living-room (192.0.2.1:23):"), "{}", stdout);
+    assert!(stdout.contains("office (192.0.2.2:2323):"), "{}", stdout);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:tv"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--device"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn switch_routes_over_serial() {
+    let mock = MockSwitch::start_serial();
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.commands(), vec!["SET SW hdmiin2 hdmiout4"]);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "status"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["routing"][3]["input"], "hdmiin2");
+}
+
+#[test]
+fn switch_routes_over_raw_tcp() {
+    let mock = MockSwitch::start_without_banner();
+    let configuration = mock.configuration(&format!("  transport:\n    type: raw-tcp\n{}", ALIASES));
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.commands(), vec!["SET SW hdmiin2 hdmiout4"]);
+    assert_eq!(mock.route(Output::Hdmi4), Input::Hdmi2);
+
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(&format!(
+        "  transport:\n    type: raw-tcp\n    welcome_message: true\n{}",
+        ALIASES
+    ));
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "status"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["routing"][0]["input"], "hdmiin1");
+}
+
+#[test]
+fn edid_is_set_from_presets_and_outputs() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["edid", "set", "ps", "1080p_6ch"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.edid(Input::Hdmi2), "1080p_6ch");
+
+    let output = hdmi_switch(configuration.path(), &["edid", "set", "pc", "tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.edid(Input::Hdmi1), "copy_hdmiout4");
+
+    let output = hdmi_switch(configuration.path(), &["--output-format", "json", "edid", "get"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["edid"][0]["edid"], "copy_hdmiout4");
+    assert_eq!(document["edid"][1]["edid"], "1080p_6ch");
+    assert_eq!(document["edid"][2]["edid"], "4k60hz_2ch");
+
+    let output = hdmi_switch(configuration.path(), &["edid", "get", "pc"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("copy from hdmiout4 (tv)"), "{}", stdout);
+
+    let output = hdmi_switch(configuration.path(), &["edid", "get", "ps"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("hdmiin2 (ps):"), "{}", stdout);
+    assert!(stdout.contains("1080p_6ch"), "{}", stdout);
+
+    let commands = mock.commands().len();
+    let output = hdmi_switch(configuration.path(), &["edid", "set", "ps", "8k"]);
+
+    assert_eq!(output.status.code(), Some(25));
+    assert_eq!(mock.commands().len(), commands);
+}
+
+#[test]
+fn edid_is_not_copied_from_a_disabled_output() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(&format!(
+        "{}  ports:\n    hdmiout4:\n      disabled: true\n",
+        ALIASES
+    ));
+
+    let output = hdmi_switch(configuration.path(), &["edid", "set", "pc", "tv"]);
+
+    assert_eq!(output.status.code(), Some(23));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("disabled"));
+    assert!(mock.commands().is_empty());
+}
+
+#[test]
+fn edid_list_presets_does_not_connect() {
+    let mut configuration = NamedTempFile::new().unwrap();
+    write!(configuration, "server:\n  host: 192.0.2.1\n").unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["edid", "list-presets"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("4k60hz_hdr_8ch:"), "{}", stdout);
+    assert!(stdout.contains("1920x1080p60, 2 channel audio"), "{}", stdout);
+}
+
+#[test]
+fn cec_commands_use_output_aliases() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(ALIASES);
+
+    for args in [
+        &["cec", "power-on", "tv"][..],
+        &["cec", "power-off", "all"],
+        &["cec", "input-source", "monitor"],
+        &["cec", "volume", "tv", "down"],
+    ] {
+        let output = hdmi_switch(configuration.path(), args);
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    assert_eq!(
+        mock.commands(),
+        vec![
+            "SET CEC hdmiout4 on",
+            "SET CEC all off",
+            "SET CEC hdmiout1 source",
+            "SET CEC hdmiout4 voldown",
+        ]
+    );
+
+    let output = hdmi_switch(configuration.path(), &["cec", "power-on", "projector"]);
+
+    assert_eq!(output.status.code(), Some(20));
+    assert_eq!(mock.commands().len(), 4);
+
+    let output = hdmi_switch(configuration.path(), &["cec", "power-on", "all"]);
+
+    assert_eq!(output.status.code(), Some(22));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("power-off"));
+    assert_eq!(mock.commands().len(), 4);
+}
+
+#[test]
+fn routes_can_power_on_the_display() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(&format!(
+        "\
+{}scenes:
+  movie-night:
+    - input: ps
+      output: tv
+      power_on: true
+    - input: pc
+      output: monitor
+",
+        ALIASES
+    ));
+
+    let output = hdmi_switch(configuration.path(), &["scene", "movie-night"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        mock.commands(),
+        vec!["SET SW hdmiin2 hdmiout4", "SET SW hdmiin1 hdmiout1", "SET CEC hdmiout4 on"]
+    );
+
+    let output = hdmi_switch(configuration.path(), &["switch", "--power-on", "pc:tv"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.commands()[3..], ["SET SW hdmiin1 hdmiout4", "SET CEC hdmiout4 on"]);
+}
+
+#[test]
+fn displays_failing_to_power_on_do_not_stop_the_routing() {
+    let mock = MockSwitch::start();
+    mock.set_cec_failing(true);
+    let configuration = mock.configuration(ALIASES);
+
+    let output = hdmi_switch(configuration.path(), &["switch", "--power-on", "ps:tv", "pc:all"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Every route was applied"), "{}", stderr);
+    assert!(stderr.contains("Warning: hdmiout1 (monitor): "), "{}", stderr);
+    assert_eq!(mock.route(Output::Hdmi3), Input::Hdmi1);
+    assert_eq!(
+        mock.commands()[..3],
+        ["SET SW hdmiin2 hdmiout4", "SET SW hdmiin1 all", "SET CEC hdmiout4 on"]
+    );
+    assert_eq!(mock.commands().len(), 7);
+}
+
+#[test]
+fn audio_is_set_on_outputs_and_audio_outputs() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration(&format!(
+        "\
+{}audio:
+  aliases:
+    amp: audioout1
+  ports:
+    audioout2:
+      name: \"Kitchen\"
+      disabled: true
+",
+        ALIASES
+    ));
+
+    for args in [
+        &["audio", "set", "amp", "ps"][..],
+        &["audio", "set", "tv", "arc:tv"],
+        &["audio", "set", "monitor", "ARC:monitor"],
+        &["audio", "mute", "monitor"],
+        &["audio", "arc", "tv", "on"],
+    ] {
+        let output = hdmi_switch(configuration.path(), args);
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    assert_eq!(
+        mock.commands(),
+        vec![
+            "SET AUDIO audioout1 hdmiin2",
+            "SET AUDIO hdmiout4 arc_hdmiout4",
+            "SET AUDIO hdmiout1 arc_hdmiout1",
+            "SET MUTE hdmiout1 on",
+            "SET ARC hdmiout4 on",
+        ]
+    );
+    assert_eq!(mock.audio(AudioTarget::Analog(AudioOutput::Analog1)), "hdmiin2");
+    assert!(mock.muted(AudioTarget::Hdmi(Output::Hdmi1)));
+    assert!(mock.arc(Output::Hdmi4));
+
+    let output = hdmi_switch(configuration.path(), &["audio", "unmute", "audioout2"]);
+    assert_eq!(output.status.code(), Some(23));
+    let output = hdmi_switch(configuration.path(), &["audio", "set", "speakers", "ps"]);
+    assert_eq!(output.status.code(), Some(20));
+    let output = hdmi_switch(configuration.path(), &["audio", "arc", "all", "on"]);
+    assert_eq!(output.status.code(), Some(22));
+    assert_eq!(mock.commands().len(), 5);
+}
+
+#[test]
+fn audio_get_prints_every_output() {
+    let mock = MockSwitch::start();
+    let configuration =
+        mock.configuration(&format!("{}audio:\n  aliases:\n    amp: audioout1\n", ALIASES));
+
+    let output = hdmi_switch(configuration.path(), &["audio", "set", "amp", "ps"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = hdmi_switch(configuration.path(), &["audio", "mute", "tv"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output =
+        hdmi_switch(configuration.path(), &["--output-format", "json", "audio", "get"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let audio = document["audio"].as_array().unwrap();
+    assert_eq!(audio.len(), 8);
+    assert_eq!(audio[3]["output"], "hdmiout4");
+    assert_eq!(audio[3]["source"], "follow");
+    assert_eq!(audio[3]["muted"], true);
+    assert_eq!(audio[3]["arc"], false);
+    assert_eq!(audio[4]["output"], "audioout1");
+    assert_eq!(audio[4]["source"], "hdmiin2");
+    assert_eq!(audio[4]["source_display"], "hdmiin2 (ps)");
+    assert!(audio[4].get("arc").is_none());
+
+    let output = hdmi_switch(configuration.path(), &["audio", "get", "amp"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("audioout1 (amp):"), "{}", stdout);
+    assert!(stdout.contains("hdmiin2 (ps)"), "{}", stdout);
+}
+
+#[test]
+fn api_names_are_matched_without_regard_to_case() {
+    let mock = MockSwitch::start();
+    let configuration = mock.configuration("input:\n  aliases:\n    ps: HDMIIN2\n");
+
+    let output = hdmi_switch(configuration.path(), &["switch", "ps:HDMIOUT4", "HdmiIn3:hdmiout1"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mock.commands(), vec!["SET SW hdmiin2 hdmiout4", "SET SW hdmiin3 hdmiout1"]);
+}
+
+#[test]
+fn unknown_argument_exits_with_usage_error() {
+    let configuration = NamedTempFile::new().unwrap();
+
+    let output = hdmi_switch(configuration.path(), &["--bogus"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Error: Found argument '--bogus'"), "{}", stderr);
+}
+
+#[test]
+fn ls_shows_port_names_next_to_aliases() {
+    let configuration = MockSwitch::start().configuration(
+        "input:\n  ports:\n    hdmiin2:\n      name: PlayStation\n      aliases: [ps]\n      \
+         description: Living room PS5\n",
+    );
+
+    let output = hdmi_switch(configuration.path(), &["ls"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with(
+            "Aliases:\n  Input aliases:\n    ps:       hdmiin2 PlayStation (ps) Living room PS5\n"
+        ),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,403 @@
+//! A stand-in for the 4KMX44-H2 used by the integration tests. It listens on
+//! a local TCP port, greets every connection with a banner the way the real
+//! unit does and answers `SET SW` and `GET SW` from an in-memory routing
+//! matrix, along with the EDID, CEC and audio commands. The serial variant
+//! answers on a pseudo-terminal instead.
+
+#![allow(dead_code)]
+
+use hdmi_switch::audio::AudioTarget;
+use hdmi_switch::port::AudioOutput;
+use hdmi_switch::sources;
+use hdmi_switch::{Input, Output};
+use std::collections::HashMap;
+use serial2::SerialPort;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output as ProcessOutput, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+pub const BANNER: &str = "Welcome to 4KMX44-H2 HDMI Matrix\r\n";
+
+#[derive(Default)]
+struct State {
+    routing: Mutex<HashMap<Output, Input>>,
+    edid: Mutex<HashMap<Input, String>>,
+    audio: Mutex<HashMap<AudioTarget, String>>,
+    muted: Mutex<HashMap<AudioTarget, bool>>,
+    arc: Mutex<HashMap<Output, bool>>,
+    commands: Mutex<Vec<String>>,
+    failing: AtomicBool,
+    cec_failing: AtomicBool,
+    split_banner: AtomicBool,
+    silent: AtomicBool,
+}
+
+pub struct MockSwitch {
+    pub port: u16,
+    /// Terminal the serial variant answers on.
+    pub path: Option<PathBuf>,
+    state: Arc<State>,
+    // Kept open so the terminal outlives each hdmi-switch run.
+    terminal: Option<SerialPort>,
+}
+
+impl MockSwitch {
+    /// Starts the mock on a free local port. Every output starts out showing
+    /// `hdmiin1`.
+    pub fn start() -> MockSwitch {
+        MockSwitch::start_listening(true)
+    }
+
+    /// Starts the mock like [`MockSwitch::start`] but without the banner,
+    /// like an IP-to-serial gateway in front of the RS-232 port.
+    pub fn start_without_banner() -> MockSwitch {
+        MockSwitch::start_listening(false)
+    }
+
+    fn start_listening(banner: bool) -> MockSwitch {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock switch");
+        let port = listener.local_addr().unwrap().port();
+
+        let state = initial_state();
+        let listener_state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { break };
+                let state = Arc::clone(&listener_state);
+                thread::spawn(move || handle_connection(stream, &state, banner));
+            }
+        });
+
+        MockSwitch {
+            port,
+            path: None,
+            state,
+            terminal: None,
+        }
+    }
+
+    /// Starts the mock on one end of a pseudo-terminal pair, `path` is the
+    /// other end. Like the RS-232 port of the real unit it sends no banner.
+    /// The path is looked up in `/proc`, so this only works on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn start_serial() -> MockSwitch {
+        use std::os::unix::io::AsRawFd;
+
+        let (controller, terminal) = SerialPort::pair().expect("Couldn't open pseudo-terminal");
+        let path = std::fs::read_link(format!("/proc/self/fd/{}", terminal.as_raw_fd()))
+            .expect("Couldn't find pseudo-terminal path");
+
+        let state = initial_state();
+        let connection_state = Arc::clone(&state);
+        thread::spawn(move || handle_connection(controller, &connection_state, false));
+
+        MockSwitch {
+            port: 0,
+            path: Some(path),
+            state,
+            terminal: Some(terminal),
+        }
+    }
+
+    /// Makes the mock answer every command with an error, like the real unit
+    /// does for commands it doesn't accept.
+    pub fn set_failing(&self, failing: bool) {
+        self.state.failing.store(failing, Ordering::SeqCst);
+    }
+
+    /// Makes the mock answer CEC commands with an error, like it does for a
+    /// display without HDMI-CEC.
+    pub fn set_cec_failing(&self, failing: bool) {
+        self.state.cec_failing.store(failing, Ordering::SeqCst);
+    }
+
+    /// Makes the mock send its banner in two parts with a pause in between,
+    /// like a slow link does.
+    pub fn set_split_banner(&self, split: bool) {
+        self.state.split_banner.store(split, Ordering::SeqCst);
+    }
+
+    /// Makes the mock swallow commands without answering, like a switch that
+    /// has hung.
+    pub fn set_silent(&self, silent: bool) {
+        self.state.silent.store(silent, Ordering::SeqCst);
+    }
+
+    pub fn set_route(&self, input: Input, output: Output) {
+        self.state.routing.lock().unwrap().insert(output, input);
+    }
+
+    pub fn route(&self, output: Output) -> Input {
+        self.state.routing.lock().unwrap()[&output]
+    }
+
+    pub fn edid(&self, input: Input) -> String {
+        self.state.edid.lock().unwrap()[&input].clone()
+    }
+
+    pub fn audio(&self, target: AudioTarget) -> String {
+        self.state.audio.lock().unwrap()[&target].clone()
+    }
+
+    pub fn muted(&self, target: AudioTarget) -> bool {
+        self.state.muted.lock().unwrap()[&target]
+    }
+
+    pub fn arc(&self, output: Output) -> bool {
+        self.state.arc.lock().unwrap()[&output]
+    }
+
+    /// Every command received so far, without line terminators.
+    pub fn commands(&self) -> Vec<String> {
+        self.state.commands.lock().unwrap().clone()
+    }
+
+    /// Writes a configuration file pointing at the mock. `rest` is appended
+    /// after the `server` section.
+    pub fn configuration(&self, rest: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        match &self.path {
+            Some(path) => write!(
+                file,
+                "server:\n  transport:\n    type: serial\n    path: {}\n    baud_rate: 9600\n{}",
+                path.display(),
+                rest
+            ),
+            None => write!(
+                file,
+                "server:\n  host: 127.0.0.1\n  port: {}\n{}",
+                self.port, rest
+            ),
+        }
+        .unwrap();
+        file
+    }
+}
+
+fn initial_state() -> Arc<State> {
+    let state = Arc::new(State::default());
+    for output in Output::PORTS {
+        state.routing.lock().unwrap().insert(output, Input::Hdmi1);
+    }
+    for input in Input::PORTS {
+        state.edid.lock().unwrap().insert(input, "4k60hz_2ch".to_string());
+    }
+    let targets = Output::PORTS
+        .iter()
+        .map(|output| AudioTarget::Hdmi(*output))
+        .chain(AudioOutput::PORTS.iter().map(|output| AudioTarget::Analog(*output)));
+    for target in targets {
+        state.audio.lock().unwrap().insert(target, "follow".to_string());
+        state.muted.lock().unwrap().insert(target, false);
+    }
+    for output in Output::PORTS {
+        state.arc.lock().unwrap().insert(output, false);
+    }
+
+    state
+}
+
+fn handle_connection(mut stream: impl Read + Write, state: &State, banner: bool) {
+    if banner && write_banner(&mut stream, state).is_err() {
+        return;
+    }
+
+    let mut pending: Vec<u8> = Vec::new();
+    let mut buffer = [0u8; 256];
+    loop {
+        let size = match stream.read(&mut buffer) {
+            // The serial port gives up waiting now and then, keep listening.
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => continue,
+            Ok(0) | Err(_) => return,
+            Ok(size) => size,
+        };
+        pending.extend_from_slice(&buffer[..size]);
+
+        while let Some(end) = pending.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            let line = String::from_utf8_lossy(&pending[..end]).trim().to_string();
+            pending.drain(..=end);
+            if line.is_empty() {
+                continue;
+            }
+
+            state.commands.lock().unwrap().push(line.clone());
+            if state.silent.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            let reply = respond(&line, state);
+            if stream.write_all(format!("{}\r\n", reply).as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn write_banner(stream: &mut impl Write, state: &State) -> io::Result<()> {
+    if !state.split_banner.load(Ordering::SeqCst) {
+        return stream.write_all(BANNER.as_bytes());
+    }
+
+    let (first, rest) = BANNER.split_at(BANNER.len() / 2);
+    stream.write_all(first.as_bytes())?;
+    stream.flush()?;
+    thread::sleep(Duration::from_millis(200));
+    stream.write_all(rest.as_bytes())
+}
+
+fn respond(command: &str, state: &State) -> String {
+    const FAILED: &str = "Command FAILED";
+
+    if state.failing.load(Ordering::SeqCst) {
+        return FAILED.to_string();
+    }
+
+    let fields: Vec<&str> = command.split_whitespace().collect();
+    match fields.as_slice() {
+        ["SET", "SW", input, output] => {
+            let (Ok(input), Ok(output)) = (input.parse::<Input>(), output.parse::<Output>()) else {
+                return FAILED.to_string();
+            };
+
+            let mut routing = state.routing.lock().unwrap();
+            match output {
+                Output::All => {
+                    for port in Output::PORTS {
+                        routing.insert(port, input);
+                    }
+                }
+                port => {
+                    routing.insert(port, input);
+                }
+            }
+            format!("SW {} {}", input, output)
+        }
+        ["GET", "SW", output] => match output.parse::<Output>() {
+            Ok(Output::All) | Err(_) => FAILED.to_string(),
+            Ok(output) => {
+                let input = state.routing.lock().unwrap()[&output];
+                format!("SW {} {}", input, output)
+            }
+        },
+        ["SET", "CEC", _output, _command] if state.cec_failing.load(Ordering::SeqCst) => {
+            FAILED.to_string()
+        }
+        ["SET", "CEC", output, command] => match output.parse::<Output>() {
+            Ok(output) => format!("CEC {} {}", output, command),
+            Err(_) => FAILED.to_string(),
+        },
+        ["SET", "EDID", input, edid] => {
+            let Ok(input) = input.parse::<Input>() else {
+                return FAILED.to_string();
+            };
+            state.edid.lock().unwrap().insert(input, edid.to_string());
+            format!("EDID {} {}", input, edid)
+        }
+        ["GET", "EDID", input] => match input.parse::<Input>() {
+            Ok(input) => format!("EDID {} {}", input, state.edid.lock().unwrap()[&input]),
+            Err(_) => FAILED.to_string(),
+        },
+        ["SET", "AUDIO", target, source] => {
+            let Ok(target) = target.parse::<AudioTarget>() else {
+                return FAILED.to_string();
+            };
+            state.audio.lock().unwrap().insert(target, source.to_string());
+            format!("AUDIO {} {}", target, source)
+        }
+        ["GET", "AUDIO", target] => match target.parse::<AudioTarget>() {
+            Ok(target) => format!("AUDIO {} {}", target, state.audio.lock().unwrap()[&target]),
+            Err(_) => FAILED.to_string(),
+        },
+        ["SET", "MUTE", target, muted @ ("on" | "off")] => {
+            let Ok(target) = target.parse::<AudioTarget>() else {
+                return FAILED.to_string();
+            };
+            state.muted.lock().unwrap().insert(target, *muted == "on");
+            format!("MUTE {} {}", target, muted)
+        }
+        ["GET", "MUTE", target] => match target.parse::<AudioTarget>() {
+            Ok(target) => match state.muted.lock().unwrap()[&target] {
+                true => format!("MUTE {} on", target),
+                false => format!("MUTE {} off", target),
+            },
+            Err(_) => FAILED.to_string(),
+        },
+        ["SET", "ARC", output, on @ ("on" | "off")] => match output.parse::<Output>() {
+            Ok(Output::All) | Err(_) => FAILED.to_string(),
+            Ok(output) => {
+                state.arc.lock().unwrap().insert(output, *on == "on");
+                format!("ARC {} {}", output, on)
+            }
+        },
+        ["GET", "ARC", output] => match output.parse::<Output>() {
+            Ok(Output::All) | Err(_) => FAILED.to_string(),
+            Ok(output) => match state.arc.lock().unwrap()[&output] {
+                true => format!("ARC {} on", output),
+                false => format!("ARC {} off", output),
+            },
+        },
+        _ => FAILED.to_string(),
+    }
+}
+
+/// Runs the `hdmi-switch` binary with the configuration file at `path`.
+pub fn hdmi_switch(path: &Path, args: &[&str]) -> ProcessOutput {
+    Command::new(env!("CARGO_BIN_EXE_hdmi-switch"))
+        .arg("-c")
+        .arg(path)
+        .args(args)
+        .output()
+        .expect("Couldn't run hdmi-switch")
+}
+
+/// Runs the `hdmi-switch` binary like [`hdmi_switch`], writing `stdin` to its
+/// standard input.
+pub fn hdmi_switch_with_stdin(path: &Path, args: &[&str], stdin: &str) -> ProcessOutput {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hdmi-switch"))
+        .arg("-c")
+        .arg(path)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Couldn't run hdmi-switch");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().expect("Couldn't run hdmi-switch")
+}
+
+/// Runs the `hdmi-switch` binary without `-c` from `directory`, so it looks
+/// for its configuration files. `HOME` is `directory`, the system file is
+/// `directory/etc/configuration.yaml` and `environment` is added on top of a
+/// copy of the environment without `HDMI_SWITCH_*` and `XDG_CONFIG_HOME`.
+pub fn hdmi_switch_discovering(
+    directory: &Path,
+    environment: &[(&str, &str)],
+    args: &[&str],
+) -> ProcessOutput {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_hdmi-switch"));
+    for (variable, _value) in std::env::vars() {
+        if variable.starts_with("HDMI_SWITCH_") || variable == "XDG_CONFIG_HOME" {
+            command.env_remove(variable);
+        }
+    }
+    command
+        .current_dir(directory)
+        .env("HOME", directory)
+        .env(sources::SYSTEM_PATH_VARIABLE, directory.join("etc/configuration.yaml"))
+        .envs(environment.iter().copied())
+        .args(args)
+        .output()
+        .expect("Couldn't run hdmi-switch")
+}